@@ -0,0 +1,33 @@
+//! # Mock builder-hub
+//! Runs the mock used by the end-to-end tests, to reproduce builder bugs locally.
+//! Every line typed in the terminal is sent as an event through the Colossus streams.
+//!
+//! ## Examples
+//! ```bash
+//! cargo run --example mock_hub -- 127.0.0.1:8080
+//! # Then, in the project:
+//! QUIX_BUILDER_URL=http://127.0.0.1:8080 QUIX_COLOSSUS_URL=http://127.0.0.1:8080 quix link
+//! ```
+
+#[path = "../tests/mock/mod.rs"]
+mod mock;
+
+use std::{env, io};
+
+fn main() -> io::Result<()> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    let hub = mock::MockHub::bind(addr, true)?;
+
+    eprintln!("Mock builder-hub listening on {}", hub.url);
+    eprintln!("export QUIX_BUILDER_URL={0} QUIX_COLOSSUS_URL={0}", hub.url);
+    eprintln!("Type an event (e.g. a build.status JSON) to send it to the streams.");
+
+    for line in io::stdin().lines() {
+        hub.emit(&line?);
+    }
+
+    Ok(())
+}
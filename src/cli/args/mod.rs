@@ -11,6 +11,12 @@
 //! ```bash
 //! quix link --clean
 //! ```
+//! ```bash
+//! quix link --logs-app vendor.app --logs-include "order \d+"
+//! ```
+//! ```bash
+//! quix link --once --timeout 600
+//! ```
 //!
 //! ## Panics
 //! This function will panic if the entered command does not follow any of the available.
@@ -18,6 +24,12 @@
 // ? CLI
 use clap::{arg, Arg, ArgAction, ArgMatches, ColorChoice, Command}; // CLI Argument parser
 
+use std::path::PathBuf; // Configstore location
+
+use crate::constants::routes::{BUILDER_URL, COLOSSUS_URL}; // Default endpoints
+use crate::output::{Format, Level}; // Output format and log level
+use crate::utils::gzip::Compression; // Bundle compression policy
+
 pub fn matches() -> ArgMatches {
     // ? CLI Setup
     Command::new("Quix")
@@ -41,7 +53,57 @@ pub fn matches() -> ArgMatches {
           Arg::new("verbose")
               .short('v')
               .long("verbose")
-              .action(ArgAction::Count),
+              .global(true)
+              .action(ArgAction::Count)
+              .help("More logs: -v for debug, -vv for trace, -vvv to also trace the raw stream events."),
+      )
+      .arg(
+          arg!(--"log-level" <LEVEL> "Log level: error, warn, info, debug or trace.")
+              .required(false)
+              .global(true)
+              .env("QUIX_LOG_LEVEL")
+              .value_parser(|s: &str| s.parse::<Level>())
+              .help("Log level: error, warn, info (default), debug or trace. Also sets the level of the app logs streamed."),
+      )
+      .arg(
+          arg!(--quiet "Only log the errors.")
+              .long("quiet")
+              .required(false)
+              .global(true)
+              .help("Only log the errors, without the greetings."),
+      )
+      .arg(
+          arg!(--output <FORMAT> "Output format: text or json.")
+              .required(false)
+              .global(true)
+              .env("QUIX_OUTPUT")
+              .value_parser(|s: &str| s.parse::<Format>())
+              .default_value("text")
+              .help("Output format: text (for humans) or json (one JSON object per line, for tools)."),
+      )
+      .arg(
+          arg!(--"builder-url" <URL> "Base URL of the builder-hub.")
+              .required(false)
+              .global(true)
+              .env("QUIX_BUILDER_URL")
+              .default_value(BUILDER_URL)
+              .help("Base URL of the builder-hub, e.g. a local mock."),
+      )
+      .arg(
+          arg!(--"colossus-url" <URL> "Base URL of the Colossus.")
+              .required(false)
+              .global(true)
+              .env("QUIX_COLOSSUS_URL")
+              .default_value(COLOSSUS_URL)
+              .help("Base URL of the Colossus (events and logs), e.g. a local mock."),
+      )
+      .arg(
+          arg!(--config <PATH> "Where the VTEX configstore is.")
+              .required(false)
+              .global(true)
+              .env("QUIX_CONFIGSTORE")
+              .value_parser(clap::value_parser!(PathBuf))
+              .help("Where the VTEX session is, the configstore directory or its vtex.json ($XDG_CONFIG_HOME/configstore by default)."),
       )
       .subcommand(
           Command::new("link")
@@ -61,6 +123,102 @@ pub fn matches() -> ArgMatches {
                 .required(false)
                       .conflicts_with("clean")
                       .help("Ignores some validations and links the app faster."),
+              )
+              .arg(
+                arg!(--compression <POLICY> "Bundle compression: stored, deflate[:level] or auto.")
+                .required(false)
+                .value_parser(|s: &str| s.parse::<Compression>())
+                .default_value("stored")
+                .help("Bundle compression: stored, deflate[:level] (0 to 9) or auto (text deflated, images stored)."),
+              )
+              .arg(
+                arg!(--"sticky-ttl" <SECONDS> "How long a stored sticky host is trusted by the quicker link.")
+                .required(false)
+                .env("QUIX_STICKY_TTL")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("86400")
+                .help("How long, in seconds, a stored sticky host is trusted by the quicker link (a day by default)."),
+              )
+              .arg(
+                arg!([paths] ... "The apps to link, by their folders.")
+                .required(false)
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("The apps to link, by their folders (the project in the current directory by default)."),
+              )
+              .arg(
+                arg!(--all "Link every app under the current directory.")
+                .short('a')
+                .long("all")
+                .required(false)
+                .conflicts_with("paths")
+                .help("Link every app under the current directory, e.g. the apps of a monorepo, sharing the watcher and the streams."),
+              )
+              .arg(
+                arg!(--unlink "Unlink the app when the link is stopped.")
+                .short('u')
+                .long("unlink")
+                .required(false)
+                .help("Unlink the app from the workspace when the link is stopped (Ctrl-C)."),
+              )
+              .arg(
+                arg!(--once "Link once, and exit when the build ends.")
+                .long("once")
+                .required(false)
+                .conflicts_with("quicker")
+                .help("Send the bundle, wait for the build to end and exit, with 0 if it built and 11 if it failed. For CI pipelines."),
+              )
+              .arg(
+                arg!(--timeout <SECONDS> "How long the build is waited for, with --once.")
+                .required(false)
+                .requires("once")
+                .value_parser(clap::value_parser!(u64))
+                .help("How long, in seconds, the build is waited for with --once, exiting with 12 after it (no limit by default)."),
+              )
+              .arg(
+                arg!(--"logs-app" <APP> "Only show the logs of this app.")
+                .required(false)
+                .action(ArgAction::Append)
+                .help("Only show the logs of this app, vendor.name (any version) or vendor.name@version. Repeat it for more apps."),
+              )
+              .arg(
+                arg!(--"logs-vendor" <VENDOR> "Only show the logs of the apps of this vendor.")
+                .required(false)
+                .action(ArgAction::Append)
+                .help("Only show the logs of the apps of this vendor. Repeat it for more vendors."),
+              )
+              .arg(
+                arg!(--"logs-level" <LEVEL> "Only show the app logs up to this level.")
+                .required(false)
+                .help("Only show the app logs up to this level: error, warn, info, debug or trace."),
+              )
+              .arg(
+                arg!(--"logs-include" <REGEX> "Only show the app logs matching this regex.")
+                .required(false)
+                .action(ArgAction::Append)
+                .help("Only show the app logs matching this regex, highlighting the matches. Repeat it to match any of them."),
+              )
+              .arg(
+                arg!(--"logs-exclude" <REGEX> "Hide the app logs matching this regex.")
+                .required(false)
+                .action(ArgAction::Append)
+                .help("Hide the app logs matching this regex. Repeat it to hide more."),
+              ),
+      )
+      .subcommand(
+          Command::new("unlink")
+              .about("Unlink an app, or every app, from the current workspace.")
+              .arg(
+                arg!([app] "The app to unlink, vendor.name@version.")
+                .required(false)
+                .help("The app to unlink, vendor.name@version (the project in the current directory by default)."),
+              )
+              .arg(
+                arg!(--all "Unlink every app from the workspace.")
+                .short('a')
+                .long("all")
+                .required(false)
+                .conflicts_with("app")
+                .help("Unlink every app from the workspace, e.g. before promoting it."),
               ),
       )
       .get_matches()
@@ -4,32 +4,48 @@
 //!
 //! ## Endpoints
 //! - `/link`: Link the app to the builder.
+//!
+//! ## Pooling
+//! A single client is used for the whole link session, uploads and event streams alike.
+//! Its connections are pooled, and over TLS the requests to the same host are multiplexed on one HTTP/2 connection.
+//! The client has no timeout, as the streams never end, each request to the builder and the apps API sets its own.
+
+use std::time::Duration;
 
 // HTTP Client
-use reqwest::blocking::Client;
 use reqwest::header::HeaderMap;
+use reqwest::Client;
 
 use reqwest::header::{ACCEPT, AUTHORIZATION};
 
-pub fn new(token: &str) -> Client {
-    let mut headers = HeaderMap::new();
+/// # Keepalive interval.
+/// The event streams never end, the TCP keepalive detects dead connections instead of a request timeout.
+const KEEPALIVE: Duration = Duration::from_secs(15);
 
-    headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
-    headers.insert(ACCEPT, "application/json, text/plain, */*".parse().unwrap());
+/// # Request timeout.
+/// How long a request to the builder or the apps API can take, the client itself has none as the streams never end.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 
-    reqwest::blocking::Client::builder()
-        .default_headers(headers)
-        .build()
-        .unwrap()
-}
+/// # Upload timeout.
+/// How long the bundle upload can take, longer as the whole project goes with it.
+pub const UPLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// # Idle timeout.
+/// How long an idle connection is kept in the pool.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+pub fn new(token: &str) -> Client {
+    let mut headers = HeaderMap::new();
 
-pub fn new_with_headers(token: &str, headers: &HeaderMap) -> Client {
-    let mut headers = headers.clone();
     headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
     headers.insert(ACCEPT, "application/json, text/plain, */*".parse().unwrap());
 
-    reqwest::blocking::Client::builder()
+    reqwest::Client::builder()
         .default_headers(headers)
+        .tcp_keepalive(KEEPALIVE)
+        .pool_idle_timeout(IDLE_TIMEOUT)
+        .http2_keep_alive_interval(KEEPALIVE)
+        .http2_keep_alive_while_idle(true)
         .build()
         .unwrap()
 }
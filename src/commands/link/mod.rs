@@ -8,162 +8,844 @@
 //! ```bash
 //! quix link --clean
 //! ```
+//! ```bash
+//! quix link --unlink
+//! ```
+//! ```bash
+//! quix link --logs-vendor vendor --logs-level warn
+//! ```
+//! ```bash
+//! quix link --all # Every app under the current directory, e.g. a monorepo.
+//! ```
+//! ```bash
+//! quix link --once --timeout 600 # Exits with 0 if it builds, 11 if it doesn't, 12 after 10 minutes.
+//! ```
 //!
-//! ## Panics
-//! This function will panic if the entered command does not follow any of the available.
-//! This is because the CLI will not be able to authenticate with the VTEX API.
+//! ## Errors
+//! The link stops with a `QuixError` if the session or the manifest are not valid, or if the builder refuses the project.
+//! Server errors and network blips while watching are only reported, the session goes on.
+//!
+//! ## Session
+//! The link runs as a single async loop, the watcher events, the debounce window, the Colossus streams and their signals are all selected in one place.
+//! Returning from the loop drops the session tasks, stopping the streams with it.
+//!
+//! ## Several apps
+//! With `--all` or a list of paths, each app gets its own sticky host, cache and relinks, while the watcher and the Colossus streams are shared.
+//! Each change goes to the app with the deepest root holding it.
+//!
+//! ## One-shot link
+//! With `--once`, nothing is watched: the bundles are sent, and the link ends with the builds, for CI pipelines.
+//! The build errors are printed, and the exit code tells if every app built (see `QuixError::Build` and `QuixError::Timeout`).
+//!
+//! ## Stopping
+//! Ctrl-C stops the watcher and closes the Colossus streams, then unlinks the apps if `--unlink` is set.
+//! - It's caught from the start, during the checks, the first upload or a `--once` build as well.
 
 // CLI Argument parser
 use clap::ArgMatches;
 
 // FSWatcher
-use notify::{event, Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-
-// Debouncer
-use debounce::EventDebouncer;
+use notify::{
+    event::{self, ModifyKind, RenameMode},
+    Config, Event, RecommendedWatcher, RecursiveMode, Watcher,
+};
 
-// Watcher for the link.
-use reqwest::blocking::Client;
-use serde::Deserialize;
+// Async runtime, for the link session.
+use futures_util::{future, FutureExt};
+use reqwest::{Client, Response};
 use std::{
-    env,
+    collections::BTreeMap,
+    env, fs,
+    future::Future,
+    io, mem,
     path::{Path, PathBuf},
-    thread,
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    signal,
+    sync::mpsc::{self, UnboundedReceiver},
+    task::{self, JoinError, JoinSet},
+    time,
 };
 
+// Directory walker, for directories moved into the project.
+use walkdir::WalkDir;
+
+// Zip errors, from the bundle.
+use zip::result::ZipError;
+
 // Project modules.
 use crate::{
+    clients,
+    configs::{links, project, LinkContext, Settings},
     connections::{
-        self,
-        builder::{self, RelinkBody},
-        colossus,
+        apps,
+        builder::{self, RelinkBody, RelinkFile},
+        colossus::{self, filter::LogFilter, Signal},
+    },
+    errors::{self, QuixError},
+    output::{self, Record},
+    utils::{
+        b64,
+        cache::{ChangeCache, Pending, SharedCache},
+        filter::Filter,
+        gzip::{self, Compression},
     },
-    utils::{b64, gzip},
 };
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct VTEXError {
-    code: String,
-    message: String,
+/// # Change kind.
+/// What happened to a path during the debounce window, the last event for a path wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Write,  // Created, modified or renamed into place
+    Remove, // Removed or renamed away
+}
+
+/// # Linked app.
+/// An app of the link session, with its own builder instance, change cache, ignore rules and pending changes.
+struct LinkedApp {
+    ctx: LinkContext,
+    cache: SharedCache, // Hashes of the sent files, seeded by the bundle
+    filter: Filter,     // Same rules used for the bundle, so ignored files never trigger a relink
+    changes: BTreeMap<PathBuf, Change>, // Changes collected during the current window, a map to merge repeated events
+}
+
+impl LinkedApp {
+    fn new(ctx: LinkContext) -> LinkedApp {
+        LinkedApp {
+            cache: Arc::new(Mutex::new(ChangeCache::default())),
+            filter: project_filter(&ctx),
+            changes: BTreeMap::new(),
+            ctx,
+        }
+    }
 }
 
 /// # Link command.
 /// This command will send the bundle to the builder, and watch the directory for changes.
 /// - It will return the link to the builder.
+/// - With `--all` or a list of paths, several apps are linked in the same session, sharing the watcher and the streams.
+/// - With `--once`, it only waits for the builds, and fails if any of them does.
 ///
 /// # Examples
 /// ```
 /// quix link
 /// ```
 ///
-/// # Panics
-/// If the session is not valid or the token is not set, this function will panic.
+/// # Errors
+/// If the session is not valid or the token is not set, this function will fail.
 /// This is because the CLI will not be able to authenticate with the VTEX API.
-pub fn link(args: &ArgMatches) {
-    // ? Get the path to the actual folder where the app is located, to watch.
-    let path = env::current_dir().unwrap();
+pub async fn link(args: &ArgMatches) -> Result<(), QuixError> {
+    // ! Ctrl-C ends the link at any point, instead of killing the process.
+    // ! Polled once to install the handler right away, a Ctrl-C during the sync steps is caught at the next await.
+    let stop = signal::ctrl_c().fuse();
+    tokio::pin!(stop);
+    let _ = (&mut stop).now_or_never();
 
-    let client = match connections::builder::check_availability() {
-        Ok(client) => client,
-        Err(_) => {
-            help!("Error finding a available builder, try again later.");
-            panic!("Error finding a available builder, try again later.")
+    // ? Get the path to each project root, from wherever in the project quix runs, to watch.
+    let cwd = env::current_dir()?;
+    let roots = roots(args, &cwd)?;
+    for root in roots.iter().filter(|root| **root != cwd) {
+        debug!("📂 Linking the project at {}", root.display());
+    }
+
+    let quicker = has_arg(args, "quicker");
+
+    // ? The app log filters, checked before anything is sent. Several apps share the ones where quix runs.
+    let logs = log_filter(args, if roots.len() > 1 { &cwd } else { &roots[0] })?;
+
+    if quicker {
+        info!("⚗️  Linking quicker: no manifest checks, the stored sticky host is trusted, and an unchanged bundle isn't sent again.");
+    }
+
+    // ? The session and the projects are read once, and owned by the link session.
+    let mut linked = Vec::with_capacity(roots.len());
+    for root in &roots {
+        if !quicker {
+            // ? Preflight, catch the manifest problems before the builder does.
+            preflight(root)?;
         }
-    };
 
-    fn has_arg(args: &ArgMatches, id: &str) -> bool {
-        args.get_one::<bool>(id).is_some_and(|x| *x == true)
+        linked.push(LinkedApp::new(LinkContext::new(root)?));
+    }
+
+    if linked.len() > 1 {
+        let names: Vec<String> = linked.iter().map(|app| app.ctx.app()).collect();
+        info!("🗂️  Linking {} apps: {}", linked.len(), names.join(", "));
     }
 
+    // ? The quicker link trusts the stored sticky host until it expires, skipping the availability request.
+    let max_age = if quicker {
+        let ttl = args
+            .get_one::<u64>("sticky-ttl")
+            .copied()
+            .unwrap_or_default();
+        Duration::from_secs(ttl)
+    } else {
+        Duration::ZERO
+    };
+
+    // ? A single pooled client for the whole session, the uploads and the streams of every app share its connections.
+    let client = clients::vtex::new(&linked[0].ctx.token);
+
+    // ? Each app is built by its own builder instance, resolved all at once.
+    let available = future::try_join_all(
+        linked
+            .iter_mut()
+            .map(|app| builder::check_availability(&client, &mut app.ctx, max_age)),
+    );
+    match until_stopped(&mut stop, available).await {
+        Some(result) => result?,
+        // ? Nothing was sent yet, so there's nothing to unlink.
+        None => return Ok(()),
+    };
+
     // ? Args parsing.
     if has_arg(args, "clean") {
         warn!("This feature can cause the CLI to run slower ⌛️, only use when really necessary.");
         trace!("🧹 Cleaning project cache...\n");
 
-        match builder::clean(&client) {
-            Ok(_) => {
-                debug!("⛔ Project cache cleaned.");
-            }
-            Err(e) => {
-                error!("Error cleaning project cache: {}", e);
+        let cleaned = async {
+            for app in &linked {
+                match builder::clean(&client, &app.ctx).await {
+                    Ok(_) => {
+                        debug!("⛔ Project cache of {} cleaned.", app.ctx.app());
+                    }
+                    Err(e) => {
+                        errors::report(&e);
+                    }
+                }
             }
+        };
+        if until_stopped(&mut stop, cleaned).await.is_none() {
+            return Ok(());
         }
-    } else if has_arg(args, "quicker") {
-        warn!("This feature still under development, and can cause some issues 💣.");
-        trace!("⚗️  Linking your project quicker...\n");
     }
 
-    // ? Initialize the link from the builder.
-    send_package(&path, &client);
+    // ? Bundle compression policy, stored by default.
+    let compression = args
+        .get_one::<Compression>("compression")
+        .copied()
+        .unwrap_or_default();
 
-    // ! Starts the EventSource client.
-    let c_path = path.clone();
-    let c_client = client.clone();
-    let logs = thread::spawn(|| {
-        colossus::stream(c_path, c_client);
-    });
+    // ! The session tasks, dropping the set stops them all.
+    let mut tasks = JoinSet::new();
+
+    // ! Starts the EventSource client, shared by every app, the streams signal back what they need from the session.
+    let (signal_tx, mut signals) = mpsc::unbounded_channel();
+    let start_logs = |tasks: &mut JoinSet<_>, linked: &[LinkedApp]| {
+        tasks.spawn(colossus::stream(
+            linked.iter().map(|app| app.ctx.clone()).collect(),
+            client.clone(),
+            signal_tx.clone(),
+            logs.clone(),
+        ));
+    };
+
+    let once = has_arg(args, "once");
+
+    // ? The quicker link starts the streams along with the uploads, and the upload is skipped if the builder already has this bundle.
+    // ? The one-shot link starts them first, not to miss the end of a quick build.
+    if quicker || once {
+        start_logs(&mut tasks, &linked);
+    }
+
+    let result = if once {
+        let built = link_once(&mut linked, &client, compression, &mut signals, &mut tasks);
+        let timed = async {
+            match args.get_one::<u64>("timeout") {
+                Some(secs) => time::timeout(Duration::from_secs(*secs), built)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(QuixError::Timeout(format!(
+                            "The build did not end in {}s.",
+                            secs
+                        )))
+                    }),
+                None => built.await,
+            }
+        };
+
+        until_stopped(&mut stop, timed).await.unwrap_or(Ok(()))
+    } else {
+        // ? Initialize the link from the builder, every app at once.
+        let initial = future::try_join_all(
+            linked
+                .iter_mut()
+                .map(|app| initial_link(app, &client, compression, quicker)),
+        );
+
+        match until_stopped(&mut stop, initial).await {
+            Some(Ok(_)) => {
+                if !quicker {
+                    start_logs(&mut tasks, &linked);
+                }
+
+                watch(
+                    &roots,
+                    &mut linked,
+                    &client,
+                    compression,
+                    &mut signals,
+                    &mut tasks,
+                    &mut stop,
+                )
+                .await
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(()),
+        }
+    };
+
+    // * * * Shutdown, the streams are closed before anything else. * * *
+    tasks.shutdown().await;
 
-    // * * * Starts the watcher, in the current project folder. * * *
-    let (tx, rx) = std::sync::mpsc::channel();
+    // ? The apps are unlinked whatever the link did, so the workspace is left as it was.
+    let unlinked = if has_arg(args, "unlink") {
+        unlink(&linked, &client).await
+    } else {
+        Ok(())
+    };
+
+    result.and(unlinked)
+}
+
+/// # Watch the projects.
+/// Sends the changes of every app to its builder, until Ctrl-C (`stop`) or a fatal error.
+/// - A single watcher for every app, the changes are debounced and sent in a single relink per app.
+/// - The builder asking for a new bundle (through the stream `signals`) is answered here too.
+async fn watch(
+    roots: &[PathBuf],
+    linked: &mut [LinkedApp],
+    client: &Client,
+    compression: Compression,
+    signals: &mut UnboundedReceiver<Signal>,
+    tasks: &mut JoinSet<Result<(), QuixError>>,
+    stop: &mut (impl Future + Unpin),
+) -> Result<(), QuixError> {
+    // * * * Starts the watcher, in the project folders. * * *
+    let (watch_tx, mut watch_rx) = mpsc::unbounded_channel();
 
     // This example is a little bit misleading as you can just create one Config and use it for all watchers.
     // That way the pollwatcher specific stuff is still configured, if it should be used.
     let mut watcher = RecommendedWatcher::new(
-        tx,
+        move |event| {
+            // ? The session may be gone already, then nobody is listening.
+            let _ = watch_tx.send(event);
+        },
         Config::default()
             .with_poll_interval(Duration::from_secs(1))
             .with_compare_contents(true),
-    )
-    .unwrap();
+    )?;
 
-    // watch some stuff
-    watcher.watch(&path, RecursiveMode::Recursive).unwrap();
+    // ? A single watcher for every app, an app inside another one's folder is already watched.
+    let mut watched: Vec<&Path> = vec![];
+    let mut sorted: Vec<&Path> = roots.iter().map(PathBuf::as_path).collect();
+    sorted.sort();
+    for root in sorted {
+        if !watched.iter().any(|dir| root.starts_with(dir)) {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+            watched.push(root);
+        }
+    }
 
+    // ? Debounce window, the changes are only sent after it passes without a new one.
+    // ? Only a change collected moves the deadline, the ignored paths and the stream signals don't.
     let delay = Duration::from_millis(1000);
-    let debouncer = EventDebouncer::new(delay, move |event: Event| {
-        handle_event(event, &client, &path)
-    });
+    let mut deadline: Option<time::Instant> = None;
 
-    thread::spawn(move || {
-        for e in rx {
-            match e {
+    // * The session loop, every event source of the link in a single place.
+    'session: loop {
+        tokio::select! {
+            _ = &mut *stop => {
+                info!("🛑 Stopping the link...");
+                break;
+            },
+            Some(event) = watch_rx.recv() => match event {
                 Ok(event) => {
-                    debouncer.put(event);
+                    if handle_event(event, linked) {
+                        deadline = Some(time::Instant::now() + delay);
+                    }
                 }
                 Err(e) => error!("🛑 Watcher error: {:?}", e),
+            },
+            // * Quiet window, send the changes of each app in a single relink.
+            _ = time::sleep_until(deadline.unwrap_or_else(time::Instant::now)), if deadline.is_some() => {
+                deadline = None;
+                for app in linked.iter_mut().filter(|app| !app.changes.is_empty()) {
+                    // ? A slow relink can't hold the Ctrl-C back.
+                    tokio::select! {
+                        _ = &mut *stop => {
+                            info!("🛑 Stopping the link...");
+                            break 'session;
+                        },
+                        flushed = flush(app, client, compression) => flushed?,
+                    }
+                }
             }
+            Some(signal) = signals.recv() => {
+                if let Signal::InitialLinkRequired(name) = signal {
+                    relink(linked, name, client, compression).await;
+                }
+            },
+            Some(result) = tasks.join_next() => stream_ended(result)?,
+            else => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// # Until stopped.
+/// Runs `task` to its end, unless Ctrl-C (`stop`) comes first, then it will return `None`.
+async fn until_stopped<T>(
+    stop: &mut (impl Future + Unpin),
+    task: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::select! {
+        _ = stop => {
+            info!("🛑 Stopping the link...");
+            None
+        },
+        output = task => Some(output),
+    }
+}
+
+/// # Link once.
+/// Sends the bundle of every app and waits for their builds to end, instead of watching.
+/// - The bundles are only sent once the build stream is connected, so no build ends unseen.
+/// - It will return a `QuixError::Build` naming the apps that failed (their errors are printed by the stream).
+async fn link_once(
+    linked: &mut [LinkedApp],
+    client: &Client,
+    compression: Compression,
+    signals: &mut UnboundedReceiver<Signal>,
+    tasks: &mut JoinSet<Result<(), QuixError>>,
+) -> Result<(), QuixError> {
+    loop {
+        tokio::select! {
+            Some(signal) = signals.recv() => {
+                if let Signal::Listening = signal {
+                    break;
+                }
+            },
+            Some(result) = tasks.join_next() => stream_ended(result)?,
+            else => return Err(streams_stopped()),
+        }
+    }
+
+    // ? Always the whole bundle, the builder may have another one from the last link.
+    future::try_join_all(
+        linked
+            .iter_mut()
+            .map(|app| initial_link(app, client, compression, false)),
+    )
+    .await?;
+
+    let mut pending: Vec<String> = linked.iter().map(|app| app.ctx.app_name()).collect();
+    let mut failed = vec![];
+
+    while !pending.is_empty() {
+        tokio::select! {
+            Some(signal) = signals.recv() => match signal {
+                Signal::Built { app, success } => {
+                    // ? Every pending app ended if the builder didn't tell which one.
+                    let (ended, rest): (Vec<_>, Vec<_>) = pending
+                        .into_iter()
+                        .partition(|name| app.as_ref().is_none_or(|app| app == name));
+                    pending = rest;
+
+                    if !success {
+                        failed.extend(ended);
+                    }
+                }
+                Signal::InitialLinkRequired(name) => relink(linked, name, client, compression).await,
+                Signal::Interrupted => failed.append(&mut pending),
+                Signal::Listening => {}
+            },
+            Some(result) = tasks.join_next() => stream_ended(result)?,
+            else => return Err(streams_stopped()),
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(QuixError::Build(format!(
+            "The build of {} failed.",
+            failed.join(", ")
+        )));
+    }
+
+    let names: Vec<String> = linked.iter().map(|app| app.ctx.app()).collect();
+    success!("🏁 {} built successfully.", names.join(", "));
+
+    Ok(())
+}
+
+/// # Relink.
+/// Sends the bundle of `name` again, when the builder lost its link.
+/// - Every app is sent again if the builder didn't tell which one.
+async fn relink(
+    linked: &mut [LinkedApp],
+    name: Option<String>,
+    client: &Client,
+    compression: Compression,
+) {
+    for app in linked
+        .iter_mut()
+        .filter(|app| name.as_ref().is_none_or(|name| *name == app.ctx.app_name()))
+    {
+        if let Err(e) = send_package(&mut app.ctx, client, &app.cache, compression).await {
+            errors::report(&e);
+        }
+    }
+}
+
+/// # Stream ended.
+/// The streams only stop if they can't be followed at all, it will return the reason.
+fn stream_ended(result: Result<Result<(), QuixError>, JoinError>) -> Result<(), QuixError> {
+    match result {
+        Ok(result) => result,
+        Err(_) => Err(streams_stopped()),
+    }
+}
+
+fn streams_stopped() -> QuixError {
+    QuixError::Colossus("The event streams stopped unexpectedly.".to_string())
+}
+
+/// # Unlink the apps.
+/// Unlinks every app of the session from the workspace.
+async fn unlink(linked: &[LinkedApp], client: &Client) -> Result<(), QuixError> {
+    for app in linked {
+        let ctx = &app.ctx;
+        apps::unlink(client, &ctx.account, &ctx.workspace, &ctx.app()).await?;
+        success!(
+            "🔗 {} unlinked from {}/{}.",
+            ctx.app(),
+            ctx.account,
+            ctx.workspace
+        );
+    }
+
+    Ok(())
+}
+
+fn has_arg(args: &ArgMatches, id: &str) -> bool {
+    args.get_one::<bool>(id).is_some_and(|x| *x)
+}
+
+/// # Project roots.
+/// The apps to link, each one by its root:
+/// - With `--all`, every app under the current directory (e.g. the apps of a monorepo).
+/// - With paths, the app around each one.
+/// - Otherwise, the project around the current directory.
+fn roots(args: &ArgMatches, cwd: &Path) -> Result<Vec<PathBuf>, QuixError> {
+    if has_arg(args, "all") {
+        let roots = project::discover(cwd);
+        if roots.is_empty() {
+            return Err(QuixError::Manifest(format!(
+                "No app found under {}.",
+                cwd.display()
+            )));
+        }
+
+        return Ok(roots);
+    }
+
+    let mut roots = vec![];
+    for path in args.get_many::<PathBuf>("paths").into_iter().flatten() {
+        let root = project::find_root(&fs::canonicalize(cwd.join(path))?)?;
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+
+    if roots.is_empty() {
+        roots.push(project::find_root(cwd)?);
+    }
+
+    Ok(roots)
+}
+
+/// # Preflight checks.
+/// Validates the `manifest.json` before anything is sent, skipped by the quicker link.
+fn preflight(path: &Path) -> Result<(), QuixError> {
+    let project = project::get_project(path.to_path_buf())?;
+
+    let problems = project.check(path);
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            warn!("{}", problem);
         }
-    })
-    .join()
-    .unwrap();
 
-    // * Waits for the logs thread to finish.
-    logs.join().unwrap();
+        return Err(QuixError::Manifest(format!(
+            "The manifest.json has {} problem(s).",
+            problems.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// # Log filter.
+/// The app log filters of the `quix.json`, each one given on the command line replacing it.
+fn log_filter(args: &ArgMatches, root: &Path) -> Result<LogFilter, QuixError> {
+    let mut settings = Settings::read(root)?.logs;
+
+    let values = |id: &str| -> Vec<String> {
+        args.get_many::<String>(id)
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
+
+    for (id, setting) in [
+        ("logs-app", &mut settings.apps),
+        ("logs-vendor", &mut settings.vendors),
+        ("logs-include", &mut settings.include),
+        ("logs-exclude", &mut settings.exclude),
+    ] {
+        let values = values(id);
+        if !values.is_empty() {
+            *setting = values;
+        }
+    }
+
+    if let Some(level) = args.get_one::<String>("logs-level") {
+        settings.level = Some(level.clone());
+    }
+
+    let filter = LogFilter::new(settings)?;
+    if !filter.is_empty() {
+        debug!("🔎 Only the app logs that match the filters are shown.");
+    }
+
+    Ok(filter)
+}
+
+/// # Refresh the context.
+/// Reads the `manifest.json` again when it changes during the link, picking up new builders.
+/// - A new app (e.g. a version bump) is a new link, so the sticky host is resolved again and the bundle is sent.
+/// - It will return `true` if the bundle was sent.
+async fn refresh(
+    ctx: &mut LinkContext,
+    client: &Client,
+    cache: &SharedCache,
+    compression: Compression,
+    filter: &mut Filter,
+) -> Result<bool, QuixError> {
+    let changed = ctx.refresh()?;
+    *filter = project_filter(ctx);
+
+    if !changed {
+        return Ok(false);
+    }
+
+    info!("📝 The manifest changed, linking {} now.", ctx.app());
+
+    builder::check_availability(client, ctx, Duration::ZERO).await?;
+    send_package(ctx, client, cache, compression).await?;
+
+    Ok(true)
+}
+
+/// # Initial link.
+/// Sends the bundle of the app, unless the quicker link finds the builder already has it.
+async fn initial_link(
+    app: &mut LinkedApp,
+    client: &Client,
+    compression: Compression,
+    quicker: bool,
+) -> Result<(), QuixError> {
+    if quicker && is_linked(&app.ctx, &app.cache).await {
+        success!(
+            "⚡ Nothing changed since the last link of {}, skipping the upload.",
+            app.ctx.app()
+        );
+        return Ok(());
+    }
+
+    send_package(&mut app.ctx, client, &app.cache, compression).await
 }
 
-fn choose_action(paths: Vec<PathBuf>, client: &Client, path: &Path) {
-    let filter_paths = paths.clone();
-    for path in filter_paths {
-        let path = path.to_str().unwrap();
-        if path.contains(".git") || path.contains("node_modules") {
-            return;
+/// # Flush the changes.
+/// Sends the changes of the app collected during the window, reading its manifest again if it's one of them.
+/// - A server error or a network blip is only reported, the session goes on.
+async fn flush(
+    app: &mut LinkedApp,
+    client: &Client,
+    compression: Compression,
+) -> Result<(), QuixError> {
+    // ? The context is refreshed when the manifest changes.
+    if app
+        .changes
+        .contains_key(&app.ctx.root.join("manifest.json"))
+    {
+        match refresh(
+            &mut app.ctx,
+            client,
+            &app.cache,
+            compression,
+            &mut app.filter,
+        )
+        .await
+        {
+            // * The bundle was sent again, along with every change.
+            Ok(true) => app.changes.clear(),
+            Ok(false) => {}
+            // ? A manifest being edited may not parse, the previous context is kept.
+            Err(e) => errors::report(&e),
         }
     }
 
-    if paths.len() > 1 {
-        send_package(path, client)
+    if app.changes.is_empty() {
+        return Ok(());
+    }
+
+    match send_files(
+        mem::take(&mut app.changes),
+        &mut app.ctx,
+        client,
+        &app.cache,
+    )
+    .await
+    {
+        Ok(()) => Ok(()),
+        Err(e) if e.is_transient() => {
+            errors::report(&e);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// # Is linked.
+/// Tells if the local bundle is the same as the last one successfully linked.
+/// - The change cache gets seeded on the way, as no bundle will do it if the upload is skipped.
+async fn is_linked(ctx: &LinkContext, cache: &SharedCache) -> bool {
+    let Some(last) = links::last(&links::key(ctx)) else {
+        return false;
+    };
+
+    // ? Hashing reads the whole project, so it runs on the blocking pool.
+    let (root, filter, t_cache) = (ctx.root.clone(), project_filter(ctx), cache.clone());
+    let hashed = task::spawn_blocking(move || gzip::hash(&root, &filter, &t_cache)).await;
+
+    match hashed {
+        Ok(Ok(())) => cache.lock().unwrap().digest() == last,
+        Ok(Err(e)) => {
+            trace!("Could not hash the project: {}", e);
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+/// # Project filter.
+/// The ignore rules of the project, scoped to the builders declared in the `manifest.json`.
+fn project_filter(ctx: &LinkContext) -> Filter {
+    Filter::new(&ctx.root).with_builders(ctx.builders.keys())
+}
+
+/// # Route a path.
+/// The app a path belongs to, the one with the deepest root holding it (an app can live inside another one's folder).
+fn route<'a>(linked: &'a mut [LinkedApp], path: &Path) -> Option<&'a mut LinkedApp> {
+    linked
+        .iter_mut()
+        .filter(|app| path.starts_with(&app.ctx.root))
+        .max_by_key(|app| app.ctx.root.components().count())
+}
+
+/// # Collect a change.
+/// Records the change of `path` in the app holding it, it will return `false` if nothing was recorded (e.g. an ignored path).
+fn collect(path: PathBuf, change: Change, linked: &mut [LinkedApp]) -> bool {
+    let Some(app) = route(linked, &path) else {
+        return false;
+    };
+    let filter = &app.filter;
+
+    // ? Drop the paths matched by the ignore rules.
+    if filter.is_ignored(&path) {
+        return false;
+    }
+
+    // ? A directory written into place (e.g. moved into the project) has its files sent one by one.
+    if change == Change::Write && path.is_dir() {
+        let mut collected = false;
+        WalkDir::new(&path)
+            .into_iter()
+            .filter_entry(|e| !filter.is_ignored(e.path()))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .for_each(|e| {
+                changed(e.path(), Change::Write);
+                app.changes.insert(e.into_path(), Change::Write);
+                collected = true;
+            });
+        collected
     } else {
-        send_file(&paths[0], client)
+        changed(&path, change);
+        app.changes.insert(path, change);
+        true
     }
 }
 
-fn handle_event(event: Event, client: &Client, path: &Path) {
+/// # Changed
+/// Tells the tools about a change detected, in JSON mode.
+fn changed(path: &Path, change: Change) {
+    output::emit(Record::FileChanged {
+        path: path.display().to_string(),
+        change: match change {
+            Change::Write => "write",
+            Change::Remove => "remove",
+        }
+        .to_string(),
+    });
+}
+
+/// # Handle a watcher event.
+/// Collects the changes of the event, it will return `true` if any was recorded.
+fn handle_event(event: Event, linked: &mut [LinkedApp]) -> bool {
+    let mut collected = false;
+
     match event.kind {
         event::EventKind::Create(_) => {
             debug!("📂 File created: {:?}", event.paths);
-            choose_action(event.paths, client, path)
+            for path in event.paths {
+                collected |= collect(path, Change::Write, linked)
+            }
+        }
+        event::EventKind::Modify(ModifyKind::Name(mode)) => {
+            debug!("🚚 File renamed: {:?}", event.paths);
+            match (mode, event.paths.as_slice()) {
+                // * Both sides known, the old path goes away and the new one is sent.
+                (RenameMode::Both, [from, to]) => {
+                    collected |= collect(from.clone(), Change::Remove, linked);
+                    collected |= collect(to.clone(), Change::Write, linked);
+                }
+                (RenameMode::From, paths) => {
+                    for path in paths {
+                        collected |= collect(path.clone(), Change::Remove, linked)
+                    }
+                }
+                (RenameMode::To, paths) => {
+                    for path in paths {
+                        collected |= collect(path.clone(), Change::Write, linked)
+                    }
+                }
+                // ? Unknown side, the file system tells us which one it is.
+                (_, paths) => {
+                    for path in paths {
+                        let change = if path.exists() {
+                            Change::Write
+                        } else {
+                            Change::Remove
+                        };
+                        collected |= collect(path.clone(), change, linked)
+                    }
+                }
+            }
         }
         event::EventKind::Modify(_) => {
             // Checks if the path is a directory.
@@ -171,93 +853,254 @@ fn handle_event(event: Event, client: &Client, path: &Path) {
                 debug!("📂 Directory modified: {:?}", event.paths);
             } else {
                 debug!("📄 File modified: {:?}", event.paths);
-                choose_action(event.paths, client, path)
+                for path in event.paths {
+                    collected |= collect(path, Change::Write, linked)
+                }
             }
         }
         event::EventKind::Remove(_) => {
             debug!("🗑️ File removed: {:?}", event.paths);
-            choose_action(event.paths, client, path)
+            for path in event.paths {
+                collected |= collect(path, Change::Remove, linked)
+            }
         }
         _ => {}
     }
+
+    collected
 }
 
-fn send_file(path: &PathBuf, client: &Client) {
-    let final_path = path.to_str().unwrap().to_string();
+/// # Relink body.
+/// Reads the changed files, skipping the ones the builder already has.
+/// - The paths sent are relative to the project `root`.
+/// - The new hashes are only staged, the cache records them once the relink goes through.
+fn relink_body(
+    changes: BTreeMap<PathBuf, Change>,
+    root: &Path,
+    cache: &SharedCache,
+) -> (RelinkBody, Pending) {
+    let mut body = RelinkBody::default();
+    let mut pending = Pending::default();
+    let mut cache = cache.lock().unwrap();
 
-    // ? Zip the file, using the zip utils.
-    let file = b64::encode(path);
-    let size = file.len();
+    for (path, change) in changes {
+        // !!! The watcher only reports paths inside the root, but just in case, the others are skipped.
+        let Some(p) = builder_path(&path, root) else {
+            trace!("{} is outside the project, skipping it.", path.display());
+            continue;
+        };
 
-    let relative_path = final_path
-        .split(env::current_dir().unwrap().to_str().unwrap())
-        .collect::<Vec<&str>>()[1];
+        // ? A written file can still be gone by the end of the window, then it's a removal.
+        if change == Change::Write && path.is_file() {
+            // ? A file that can't be read (e.g. locked by an editor mid-save) is skipped, not sent empty.
+            let buffer = match fs::read(&path) {
+                Ok(buffer) => buffer,
+                Err(e) => {
+                    warn!("Could not read {}, skipping it: {}", p, e);
+                    continue;
+                }
+            };
 
-    // * Thats definitely not the beauty way to do this, but it works.
-    // ? The zip writer in windows devices, uses \\ to separate directories, but when handling it on linux, it uses /, this creates a problem, here we replace it.
-    let mut p = str::replace(relative_path, "\\", "/"); // Replace the backslashes with slashes.
-    if p.starts_with('/') {
-        p.remove(0);
-    } // Remove the first '/' if exists.
+            // ? Skip the upload if the content is the same we already sent.
+            if !cache.stage(&path, &buffer, &mut pending) {
+                debug!(
+                    "⏭️  {} is unchanged, skipping it. ({} skipped this session)",
+                    p,
+                    cache.skipped()
+                );
+                continue;
+            }
 
-    let body = RelinkBody {
-        content: file,
-        byte_size: size,
-        path: p,
-    };
+            // ? Encode the file, using the b64 utils.
+            let file = b64::encode(&buffer);
+            let size = file.len();
 
-    // ? Send the file to the builder.
-    match builder::relink(client, body) {
-        Ok(resp) => {
-            if resp.status().is_success() {
-                // trace!(resp.text().unwrap().as_str());
-                success!("Successfully sent the 💫 file to the builder.");
-            } else if resp.status().is_server_error() {
-                let error: VTEXError = resp.json().unwrap();
-                // !!! Panic if the resp is not a success.
-                help!("This looks like a Server Error (500ish). Please try again later.");
-                error!("{:?}: {}", error.code, error.message);
-            } else {
-                let error: VTEXError = resp.json().unwrap();
-                // !!! Panic if the resp is not a success.
-                help!(
-                    "This looks like an Error. Please check your internet connection and try again."
-                );
-                fatal!("{:?}: {}", error.code, error.message);
+            body.files.push(RelinkFile {
+                content: Some(file),
+                byte_size: size,
+                path: p,
+            });
+        } else if !path.is_dir() {
+            // ? A directory gone (removed or renamed away) is a single event, the builder has a file for each path under it.
+            let removed = cache.stage_removal(&path, &mut pending);
+
+            for p in removed.iter().filter_map(|path| builder_path(path, root)) {
+                // ? Already removed along with its directory.
+                if body.files.iter().any(|file| file.path == p) {
+                    continue;
+                }
+
+                trace!("🗑️ Removing {} from the builder.", p);
+                body.files.push(RelinkFile {
+                    content: None,
+                    byte_size: 0,
+                    path: p,
+                });
             }
         }
-        Err(e) => {
-            error!("{:?}", e);
-        }
     }
+
+    (body, pending)
+}
+
+/// # Builder path.
+/// The path of a project file for the builder, relative to the `root` with `/` separators, whatever the OS uses.
+fn builder_path(path: &Path, root: &Path) -> Option<String> {
+    let relative_path = path.strip_prefix(root).ok()?;
+
+    Some(
+        relative_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
 }
 
-pub fn send_package(path: &Path, client: &Client) {
+async fn send_files(
+    changes: BTreeMap<PathBuf, Change>,
+    ctx: &mut LinkContext,
+    client: &Client,
+    cache: &SharedCache,
+) -> Result<(), QuixError> {
+    // !!! The cache lock is released before the request, it can't be held across an await.
+    let (body, pending) = relink_body(changes, &ctx.root, cache);
+
+    if body.files.is_empty() {
+        return Ok(());
+    }
+
+    let count = body.files.len();
+    let start = Instant::now();
+
+    output::emit(Record::UploadStarted {
+        kind: "relink".to_string(),
+        app: ctx.app(),
+    });
+
+    // ? Send the files to the builder.
+    let mut resp = builder::relink(client, ctx, &body).await;
+
+    // ? The builder instance may have moved, retry once on the new one.
+    if builder::host_moved(&resp) && builder::reresolve(client, ctx).await {
+        resp = builder::relink(client, ctx, &body).await;
+    }
+
+    resp?;
+
+    // => The builder has the changes, only now the cache can tell them apart from the next ones.
+    let skipped = {
+        let mut cache = cache.lock().unwrap();
+        cache.commit(pending);
+        cache.skipped()
+    };
+    if skipped > 0 {
+        success!(
+            "Successfully sent {} 💫 file(s) of {} to the builder. ({} unchanged skipped this session)",
+            count,
+            ctx.app(),
+            skipped
+        );
+    } else {
+        success!(
+            "Successfully sent {} 💫 file(s) of {} to the builder.",
+            count,
+            ctx.app()
+        );
+    }
+
+    output::emit(Record::UploadFinished {
+        kind: "relink".to_string(),
+        app: ctx.app(),
+        bytes: body.to_string().len() as u64,
+        ms: start.elapsed().as_millis() as u64,
+    });
+
+    Ok(())
+}
+
+/// # Send the package.
+/// Sends the whole project to the builder, and remembers it for the quicker link.
+async fn send_package(
+    ctx: &mut LinkContext,
+    client: &Client,
+    cache: &SharedCache,
+    compression: Compression,
+) -> Result<(), QuixError> {
+    let start = Instant::now();
+
+    let mut resp = upload_package(ctx, client, cache, compression).await;
+
+    // ? The builder instance may have moved, the bundle is zipped again and sent to the new one.
+    if builder::host_moved(&resp) && builder::reresolve(client, ctx).await {
+        resp = upload_package(ctx, client, cache, compression).await;
+    }
+
+    resp?;
+
+    // => The link was sent to the builder.
+    success!(
+        "Successfully sent the bundle of {} to the builder in {:.1?}.",
+        ctx.app(),
+        start.elapsed()
+    );
+
+    // ? Remember the bundle, so the quicker link can skip sending it again.
+    let key = links::key(ctx);
+    links::save(&key, cache.lock().unwrap().digest());
+
+    Ok(())
+}
+
+/// # Upload the package.
+/// Zips the project and streams the bundle to the builder, seeding the change cache on the way.
+async fn upload_package(
+    ctx: &LinkContext,
+    client: &Client,
+    cache: &SharedCache,
+    compression: Compression,
+) -> Result<Response, QuixError> {
+    // ? The bundle seeds the cache again, files gone since the last bundle shouldn't stay in there.
+    cache.lock().unwrap().clear();
+
+    let start = Instant::now();
+
+    output::emit(Record::UploadStarted {
+        kind: "link".to_string(),
+        app: ctx.app(),
+    });
+
     // For the first link command, we need to create a new zip file, with all the files in the folder.
-    // ? Create a new zip bundle.
-    let bundle = gzip::zip(path).unwrap();
-
-    // ? Send the bundle to the builder.
-    match builder::link(client, bundle) {
-        Ok(resp) => {
-            if resp.status().is_success() {
-                // => The link was sent to the builder.
-                success!("Successfully sent the bundle to the builder.");
-            } else {
-                let error: VTEXError = resp.json().unwrap();
+    // ? Create a new zip bundle, with the ignore rules as they are now.
+    let (bundle, zipping) = gzip::zip(&ctx.root, project_filter(ctx), cache.clone(), compression)?;
 
-                // !!! Panic if the resp is not a success.
-                if error.code.as_str() == "link_on_production" {
-                    help!("Action not allowed on production, change to a development environment to link your project.");
-                }
+    // ? Send the bundle to the builder, while it's still being zipped.
+    let resp = builder::link(client, ctx, bundle).await;
 
-                help!("Check your internet connection and VTEX credentials, try logging in again.");
-                fatal!("{:?}: {}", error.code, error.message);
+    // ? The upload only ends after the zip does, so this doesn't wait for long.
+    match zipping.await {
+        Ok(Ok(stats)) => {
+            info!("📦 Bundle size of {}: {}", ctx.app(), stats);
+
+            if resp.is_ok() {
+                output::emit(Record::UploadFinished {
+                    kind: "link".to_string(),
+                    app: ctx.app(),
+                    bytes: stats.zipped,
+                    ms: start.elapsed().as_millis() as u64,
+                });
             }
         }
-        Err(e) => {
-            help!("Error while sending the bundle to the builder.");
-            error!("{:?}", e);
+        // * A failed upload also drops the bundle stream, then the upload error is the one that matters.
+        Ok(Err(ZipError::Io(e))) if e.kind() == io::ErrorKind::BrokenPipe && resp.is_err() => {}
+        // ? Otherwise the zipping failed, and the upload was aborted because of it.
+        Ok(Err(e)) => return Err(e.into()),
+        Err(_) => {
+            return Err(QuixError::Zip(ZipError::Io(io::Error::other(
+                "The zipping task stopped unexpectedly.",
+            ))))
         }
     }
+
+    resp
 }
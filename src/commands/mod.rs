@@ -1,6 +1,7 @@
 //! # Mod to handle the CLI commands and subcommands.
 //! Here are defied the CLI commands and subcommands.
 //! - `link`: Handles the `link` subcommand.
+//! - `unlink`: Handles the `unlink` subcommand.
 //!
 //! ## Examples
 //! ```bash
@@ -9,6 +10,9 @@
 //! ```bash
 //! quix link --clean
 //! ```
+//! ```bash
+//! quix unlink --all
+//! ```
 //!
 //! ## Panics
 //! This function will panic if the entered command does not follow any of the available.
@@ -16,3 +20,6 @@
 
 pub mod link;
 pub use link::link;
+
+pub mod unlink;
+pub use unlink::unlink;
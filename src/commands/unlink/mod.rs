@@ -0,0 +1,72 @@
+//! # Unlink subcommand
+//! The `unlink` subcommand is used to unlink apps from the current workspace, e.g. before it's promoted.
+//!
+//! ## Examples
+//! ```bash
+//! quix unlink
+//! ```
+//! ```bash
+//! quix unlink vendor.app@1.0.0
+//! ```
+//! ```bash
+//! quix unlink --all
+//! ```
+//!
+//! ## Errors
+//! The unlink stops with a `QuixError` if the session is not valid, or if the Apps API refuses it.
+//! Without an app, the one in the current directory `manifest.json` is unlinked.
+
+// CLI Argument parser
+use clap::ArgMatches;
+
+use std::env;
+
+// Project modules.
+use crate::{
+    clients,
+    configs::{project, Vtex},
+    connections::apps,
+    errors::QuixError,
+};
+
+/// # Unlink command.
+/// This command will unlink an app, or every app, from the current workspace.
+///
+/// # Examples
+/// ```
+/// quix unlink --all
+/// ```
+///
+/// # Errors
+/// If the session is not valid, or the app is not given and there's no `manifest.json`, this function will fail.
+pub async fn unlink(args: &ArgMatches) -> Result<(), QuixError> {
+    let session = Vtex::info()?;
+    let client = clients::vtex::new(&session.token);
+
+    if args.get_one::<bool>("all").is_some_and(|x| *x) {
+        apps::unlink_all(&client, &session.account, &session.workspace).await?;
+        success!(
+            "🔗 Every app unlinked from {}/{}.",
+            session.account,
+            session.workspace
+        );
+
+        return Ok(());
+    }
+
+    // ? Without an app, the project around the current directory is the one unlinked.
+    let app = match args.get_one::<String>("app") {
+        Some(app) => app.clone(),
+        None => project::get_project(project::find_root(&env::current_dir()?)?)?.app(),
+    };
+
+    apps::unlink(&client, &session.account, &session.workspace, &app).await?;
+    success!(
+        "🔗 {} unlinked from {}/{}.",
+        app,
+        session.account,
+        session.workspace
+    );
+
+    Ok(())
+}
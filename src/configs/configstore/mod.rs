@@ -0,0 +1,253 @@
+//! # Configstore
+//! The JSON files quix shares with the VTEX toolbelt, like `~/.config/configstore/vtex.json`.
+//! Both write to them, so quix never replaces what it doesn't own:
+//! - Updates are deep-merged into the stored file, the keys not in the update are kept as they are.
+//! - Writes go to a temporary file in the same directory, renamed over the stored one, so a reader never sees half a file.
+//! - Updates hold an advisory lock (a `.lock` file next to the stored one), so two quix sessions don't lose each other's changes.
+//!
+//! # Location
+//! The `vtex.json` is looked up in, by order:
+//! - `--config <PATH>` or `QUIX_CONFIGSTORE`, either the configstore directory or the `.json` file itself.
+//! - `$XDG_CONFIG_HOME/configstore`, as the toolbelt does.
+//! - `~/.config/configstore`.
+//!
+//! # Examples
+//! ```rust
+//! let store = ConfigStore::vtex()?;
+//! store.update(json!({ "apps": { "vendor": { "app": { "sticky-host": {} } } } }))?;
+//! ```
+//!
+//! # Errors
+//! A `QuixError::Io` if the file can't be read or written, and a `QuixError::Session` if it's not valid JSON, as it's never overwritten then.
+
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    sync::OnceLock,
+};
+
+use serde_json::{Map, Value};
+
+use crate::errors::QuixError;
+
+static LOCATION: OnceLock<PathBuf> = OnceLock::new();
+
+/// # Configure the location.
+/// Overrides where the `vtex.json` is, only the first call has any effect.
+pub fn configure(location: Option<PathBuf>) {
+    if let Some(location) = location {
+        let _ = LOCATION.set(location);
+    }
+}
+
+/// # Config home.
+/// `$XDG_CONFIG_HOME`, or `~/.config` when it's not set (or not an absolute path, as the spec asks).
+pub fn config_home() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home::home_dir().map(|home| home.join(".config")))
+}
+
+/// # VTEX path.
+/// Where the `vtex.json` is, see the lookup order above.
+pub fn vtex_path() -> Result<PathBuf, QuixError> {
+    if let Some(location) = LOCATION.get() {
+        return Ok(if location.extension().is_some_and(|ext| ext == "json") {
+            location.clone()
+        } else {
+            location.join("vtex.json")
+        });
+    }
+
+    config_home()
+        .map(|dir| dir.join("configstore/vtex.json"))
+        .ok_or_else(|| QuixError::Session("No home directory found.".to_string()))
+}
+
+/// # ConfigStore struct.
+/// A shared JSON file.
+#[derive(Debug, Clone)]
+pub struct ConfigStore {
+    path: PathBuf,
+}
+
+/// # Implements the `ConfigStore` and define the **vtex**, **read** and **update** methods.
+impl ConfigStore {
+    pub fn new(path: impl Into<PathBuf>) -> ConfigStore {
+        ConfigStore { path: path.into() }
+    }
+
+    /// # VTEX configstore.
+    /// The `vtex.json` of the toolbelt, with the session and the sticky hosts.
+    pub fn vtex() -> Result<ConfigStore, QuixError> {
+        Ok(ConfigStore::new(vtex_path()?))
+    }
+
+    /// # Read the file.
+    /// It will return the stored JSON, an empty object if there's no file yet.
+    pub fn read(&self) -> Result<Value, QuixError> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+                QuixError::Session(format!("Could not parse {}: {}", self.path.display(), e))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Value::Object(Map::new())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// # Update the file.
+    /// Deep-merges `patch` into the stored JSON, under the lock, and writes it atomically.
+    pub fn update(&self, patch: Value) -> Result<(), QuixError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // ! Held until the new file is in place, released on drop.
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sibling("lock"))?;
+        lock.lock()?;
+
+        let mut stored = self.read()?;
+        merge(&mut stored, patch);
+
+        self.write(&stored)
+    }
+
+    /// # Write the file.
+    /// Writes to a temporary file and renames it over the stored one, keeping its permissions.
+    fn write(&self, value: &Value) -> Result<(), QuixError> {
+        let temp = self.sibling(&format!("{}.tmp", process::id()));
+
+        let result = (|| -> io::Result<()> {
+            let mut file = File::create(&temp)?;
+            file.write_all(serde_json::to_string_pretty(value)?.as_bytes())?;
+            file.sync_all()?;
+
+            // ? The session has a token, the permissions of the stored file are kept (e.g. `600`).
+            if let Ok(metadata) = fs::metadata(&self.path) {
+                fs::set_permissions(&temp, metadata.permissions())?;
+            }
+
+            fs::rename(&temp, &self.path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+
+        Ok(result?)
+    }
+
+    /// # Sibling file.
+    /// A hidden file next to the stored one, e.g. `.vtex.json.lock`.
+    fn sibling(&self, extension: &str) -> PathBuf {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        self.path.with_file_name(format!(".{}.{}", name, extension))
+    }
+}
+
+/// # Deep merge.
+/// Merges the `patch` objects into the `target` ones, anything else in `patch` replaces what's in `target`.
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// # Store
+    /// A throwaway `vtex.json` in its own directory, removed on drop.
+    struct Store(ConfigStore);
+
+    impl Drop for Store {
+        fn drop(&mut self) {
+            if let Some(dir) = self.0.path.parent() {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    fn store(name: &str) -> Store {
+        let dir = env::temp_dir().join(format!("quix-configstore-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Store(ConfigStore::new(dir.join("vtex.json")))
+    }
+
+    #[test]
+    fn merges_the_nested_objects() {
+        let mut target = json!({ "login": "me", "apps": { "vendor": { "a": 1 } } });
+        merge(
+            &mut target,
+            json!({ "apps": { "vendor": { "b": 2 }, "other": {} } }),
+        );
+
+        assert_eq!(
+            target,
+            json!({ "login": "me", "apps": { "vendor": { "a": 1, "b": 2 }, "other": {} } })
+        );
+    }
+
+    #[test]
+    fn replaces_anything_but_objects() {
+        let mut target = json!({ "list": [1, 2], "value": { "nested": true }, "kept": "yes" });
+        merge(&mut target, json!({ "list": [3], "value": null }));
+
+        assert_eq!(target, json!({ "list": [3], "value": null, "kept": "yes" }));
+
+        let mut target = json!("scalar");
+        merge(&mut target, json!({ "key": "value" }));
+        assert_eq!(target, json!({ "key": "value" }));
+    }
+
+    #[test]
+    fn keeps_the_keys_it_does_not_own() {
+        let store = store("update");
+        assert_eq!(store.0.read().unwrap(), json!({}));
+
+        store
+            .0
+            .update(json!({ "token": "abc", "env": "prod" }))
+            .unwrap();
+        store.0.update(json!({ "token": "def" })).unwrap();
+
+        assert_eq!(
+            store.0.read().unwrap(),
+            json!({ "token": "def", "env": "prod" })
+        );
+    }
+
+    #[test]
+    fn never_overwrites_an_invalid_file() {
+        let store = store("invalid");
+        fs::create_dir_all(store.0.path.parent().unwrap()).unwrap();
+        fs::write(&store.0.path, "{ not json").unwrap();
+
+        assert!(matches!(
+            store.0.update(json!({ "token": "abc" })),
+            Err(QuixError::Session(_))
+        ));
+        assert_eq!(fs::read_to_string(&store.0.path).unwrap(), "{ not json");
+    }
+}
@@ -0,0 +1,94 @@
+//! # Link context.
+//! The session and project data of a link, read once per run and shared by the builder, Colossus and route modules.
+//! So the `manifest.json` and `vtex.json` are not read again for every request.
+//!
+//! # Examples
+//! ```rust
+//! let ctx = LinkContext::new(&path)?;
+//! ctx.app(); // vendor.name@version
+//! ```
+//!
+//! # Refreshing
+//! The context only changes on purpose, `refresh` reads the `manifest.json` again (e.g. after a version bump).
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::errors::QuixError;
+
+use super::{project, Vtex};
+
+/// # LinkContext struct.
+/// Everything a link needs to know about the session and the project.
+#[derive(Debug, Clone)]
+pub struct LinkContext {
+    // * Session Data
+    pub account: String,
+    pub workspace: String,
+    pub token: String,
+
+    // * Project Data
+    pub vendor: String,
+    pub name: String,
+    pub version: String,
+    pub builders: HashMap<String, String>, // Builder name => builder version
+
+    // * Link Data
+    pub sticky_host: Option<String>, // Builder instance, once resolved
+    pub root: PathBuf,               // Project root, where the `manifest.json` is
+}
+
+/// # Implements the `LinkContext` and define the **new**, **app**, **app_name** and **refresh** methods.
+impl LinkContext {
+    pub fn new(root: &Path) -> Result<LinkContext, QuixError> {
+        let session = Vtex::info()?;
+        let project = project::get_project(root.to_path_buf())?;
+
+        Ok(LinkContext {
+            account: session.account,
+            workspace: session.workspace,
+            token: session.token,
+            vendor: project.vendor,
+            name: project.name,
+            version: project.version,
+            builders: project.builders,
+            sticky_host: None,
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// # App
+    /// The app identifier used by the builder, `vendor.name@version`.
+    pub fn app(&self) -> String {
+        format!("{}.{}@{}", self.vendor, self.name, self.version)
+    }
+
+    /// # App name
+    /// The app identifier without its version, `vendor.name`.
+    pub fn app_name(&self) -> String {
+        format!("{}.{}", self.vendor, self.name)
+    }
+
+    /// # Refresh the project data.
+    /// Reads the `manifest.json` again, the session stays the same.
+    /// - It will return `true` if the app changed (vendor, name or version), as it's a new link for the builder.
+    pub fn refresh(&mut self) -> Result<bool, QuixError> {
+        let project = project::get_project(self.root.clone())?;
+        let app = self.app();
+
+        self.vendor = project.vendor;
+        self.name = project.name;
+        self.version = project.version;
+        self.builders = project.builders;
+
+        let changed = self.app() != app;
+        if changed {
+            // ? The sticky host belongs to the app version, it has to be resolved again.
+            self.sticky_host = None;
+        }
+
+        Ok(changed)
+    }
+}
@@ -0,0 +1,52 @@
+//! # Last links, stored by quix.
+//! Keeps the bundle digest of the last successful link of each app, in `~/.config/quix/links.json` (or under `$XDG_CONFIG_HOME`).
+//! With that, `--quicker` can skip uploading a bundle the builder already has.
+//!
+//! # Examples
+//! ```rust
+//! links::save(&key, digest);
+//! links::last(&key) == Some(digest); // true
+//! ```
+
+use serde_json::{json, Map, Value};
+use std::{fs, path::PathBuf};
+
+use super::{configstore, ConfigStore, LinkContext};
+
+/// # Links file path.
+/// Where the digests are stored, `None` if there's no home directory.
+fn file() -> Option<PathBuf> {
+    configstore::config_home().map(|dir| dir.join("quix/links.json"))
+}
+
+/// # Read the links file.
+/// A missing or broken file is just an empty one, it's only a cache.
+fn read() -> Map<String, Value> {
+    file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// # Link key.
+/// Identifies a link, the same app version can be linked to many accounts and workspaces.
+pub fn key(ctx: &LinkContext) -> String {
+    format!("{}/{}/{}", ctx.account, ctx.workspace, ctx.app())
+}
+
+/// # Last link digest.
+/// The digest of the last bundle successfully linked with this key, if any.
+pub fn last(key: &str) -> Option<u64> {
+    read().get(key).and_then(Value::as_u64)
+}
+
+/// # Save a link digest.
+/// Records the digest of a successfully linked bundle, failures are only traced as it's just a cache.
+pub fn save(key: &str, digest: u64) {
+    let Some(path) = file() else { return };
+
+    // ? Merged into the stored links, other sessions may be saving theirs.
+    if let Err(e) = ConfigStore::new(path).update(json!({ key: digest })) {
+        trace!("Could not save the link digest: {}", e);
+    }
+}
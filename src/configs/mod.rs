@@ -11,8 +11,22 @@
 
 /// # Project struct, used to store project information.
 pub mod project;
-pub use project::Project;
 
 /// # Auth module, used to store authentication information.
 pub mod vtex;
 pub use vtex::Vtex;
+
+/// # Configstore module, used to safely update the files shared with the toolbelt.
+pub mod configstore;
+pub use configstore::ConfigStore;
+
+/// # Links module, used to store the last successful links.
+pub mod links;
+
+/// # Settings module, used to store the quix settings of a project.
+pub mod settings;
+pub use settings::Settings;
+
+/// # Context module, used to share the session and project data of a link.
+pub mod context;
+pub use context::LinkContext;
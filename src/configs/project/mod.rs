@@ -3,18 +3,24 @@
 //!
 //! # Examples
 //! ```rust
-//! let project = Project::info();
+//! let root = find_root(&env::current_dir()?)?; // From any subdirectory, like `git`.
+//! let project = get_project(root)?;
 //! ```
 //!
-//! # Panics
-//! This function will panic if the JSON file is not properly formatted.
-//! If the JSON file is not found, this function will panic.
+//! # Errors
+//! A `QuixError::Manifest` is returned if the JSON file is not found, or not properly formatted.
 
-use crate::utils::json;
+use crate::{errors::QuixError, utils::json};
 
 use serde::Deserialize;
 
-use std::{env, fs::File, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
 
 /// # Project struct.
 /// This struct will contain the project data.
@@ -24,53 +30,122 @@ pub struct Project {
     pub vendor: String,  // Vendor name
     pub name: String,    // Project name
     pub version: String, // Project version
+    #[serde(default)]
+    pub builders: HashMap<String, String>, // Builder name => builder version, each one maps to a directory
 }
 
-/// # Implements the `Project` and define the **info** method.
-/// This method will return the project struct.
-///
-/// # Examples
-/// ```rust
-/// let project = Project::info();
-/// ```
+/// # Implements the `Project` and define the **app** and **check** methods.
 impl Project {
-    pub fn info() -> Result<Project, ()> {
-        // ? Get the project directory
-        match env::current_dir() {
-            Ok(path) => match get_project(path) {
-                Ok(project) => Ok(project),
-                Err(e) => Err(error!("Project info failed: {:?}", e)),
-            },
-            Err(e) => Err(error!("Error: {}", e)),
+    /// # App
+    /// The app identifier used by the builder, `vendor.name@version`.
+    pub fn app(&self) -> String {
+        format!("{}.{}@{}", self.vendor, self.name, self.version)
+    }
+
+    /// # Check the manifest.
+    /// Looks for problems the builder would only complain about after the upload.
+    /// - It will return a description of each problem found, empty if none.
+    pub fn check(&self, root: &Path) -> Vec<String> {
+        let mut problems = vec![];
+
+        // ? Vendor and name, lowercase letters, numbers and dashes only.
+        for (field, value) in [("vendor", &self.vendor), ("name", &self.name)] {
+            let valid = !value.is_empty()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+            if !valid {
+                problems.push(format!(
+                    "The `{}` \"{}\" should only have lowercase letters, numbers and dashes.",
+                    field, value
+                ));
+            }
+        }
+
+        // ? Version, `major.minor.patch` with an optional pre-release.
+        let core = self.version.split(['-', '+']).next().unwrap_or_default();
+        let parts: Vec<&str> = core.split('.').collect();
+        if parts.len() != 3 || parts.iter().any(|p| p.parse::<u64>().is_err()) {
+            problems.push(format!(
+                "The `version` \"{}\" is not a valid semver, like 1.0.0.",
+                self.version
+            ));
+        }
+
+        // ? Builders, each one needs its own directory.
+        if self.builders.is_empty() {
+            problems.push("There are no `builders` declared, nothing would be built.".to_string());
         }
+        for builder in self.builders.keys() {
+            if !root.join(builder).is_dir() {
+                problems.push(format!(
+                    "The `{}` builder is declared, but there's no `{}/` directory.",
+                    builder, builder
+                ));
+            }
+        }
+
+        problems
     }
 }
 
+/// # Find the project root.
+/// Walks up from `start` to the nearest directory with an app `manifest.json`, so quix works from any subdirectory of the project.
+/// - The `manifest.json` files that aren't apps (e.g. a web app manifest in `public/`) are skipped, as `discover` does.
+///
+/// # Errors
+/// A `QuixError::Manifest` if neither `start` nor any of its parents has an app `manifest.json`.
+pub fn find_root(start: &Path) -> Result<PathBuf, QuixError> {
+    start
+        .ancestors()
+        .filter(|dir| dir.join("manifest.json").is_file())
+        .find(|dir| get_project(dir.to_path_buf()).is_ok())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            QuixError::Manifest(format!(
+                "No app manifest.json found in {} or any of its parents.",
+                start.display()
+            ))
+        })
+}
+
+/// # Discover the apps.
+/// The root of every app under `start`, like the apps of a monorepo.
+/// - The `manifest.json` files that aren't apps (e.g. a web app manifest) are skipped, and so are `node_modules` and the hidden folders.
+pub fn discover(start: &Path) -> Vec<PathBuf> {
+    WalkDir::new(start)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name == "node_modules" || name.starts_with('.'))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == "manifest.json")
+        .filter_map(|e| e.path().parent().map(Path::to_path_buf))
+        .filter(|root| get_project(root.clone()).is_ok())
+        .collect()
+}
+
 /// # Get the manifest data from the manifest file.
 /// This function will get the manifest from the manifest file.
 ///
-/// # Panics
-/// This function will panic if the manifest file is not found.
-/// If the file cannot be parsed correctly, this function will panic.
-pub fn get_project(path: PathBuf) -> Result<Project, ()> {
+/// # Errors
+/// A `QuixError::Manifest` if the manifest file is not found, or cannot be parsed correctly.
+pub fn get_project(path: PathBuf) -> Result<Project, QuixError> {
     // ? Join current path + `manifest.json` file
     let path = path.join("manifest.json");
 
     // ? Tries to open the file
     match File::open(path) {
         // * File exists
-        Ok(file) => {
-            match json::read(file) {
-                // * File is properly formatted
-                Ok(manifest) => Ok(manifest),
-                // * File is not properly formatted
-                Err(e) => Err(error!("JSON Error during parsing: {:?}", e)),
-            }
-        }
+        Ok(file) => json::read(file)
+            // !!! File is not properly formatted
+            .map_err(|e| {
+                QuixError::Manifest(format!("The manifest.json could not be parsed: {}", e))
+            }),
         // !!! Wasn't able to open the file
-        Err(_) => {
-            help!("Are you sure you are in a project directory?");
-            Err(error!("No manifest file found."))
-        }
+        Err(_) => Err(QuixError::Manifest("No manifest file found.".to_string())),
     }
 }
@@ -0,0 +1,57 @@
+//! # Retrieves the quix settings from `quix.json`.
+//! The settings quix reads from the project root, next to the `manifest.json`, all of them optional.
+//!
+//! # Examples
+//! ```json
+//! {
+//!   "logs": {
+//!     "apps": ["vendor.service"],
+//!     "level": "warn",
+//!     "exclude": ["healthcheck"]
+//!   }
+//! }
+//! ```
+//!
+//! # Errors
+//! A `QuixError::Config` is returned if the file is not properly formatted, a missing file is just the defaults.
+
+use std::{fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::errors::QuixError;
+
+/// # Settings struct.
+/// This struct will contain the project settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub logs: LogSettings, // Filters for the app logs streamed during a link
+}
+
+/// # Log settings struct.
+/// Which app logs are shown, every one by default.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub apps: Vec<String>,     // `vendor.name`, or `vendor.name@version`
+    pub vendors: Vec<String>,  // Every app of the vendor
+    pub level: Option<String>, // The least important level shown
+    pub include: Vec<String>,  // Regexes, a line has to match one of them
+    pub exclude: Vec<String>,  // Regexes, a line can't match any of them
+}
+
+/// # Implements the `Settings` and define the **read** method.
+impl Settings {
+    /// # Read the settings.
+    /// Reads the `quix.json` in the project root, the defaults if there's none.
+    pub fn read(root: &Path) -> Result<Settings, QuixError> {
+        match fs::read_to_string(root.join("quix.json")) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+                QuixError::Config(format!("The quix.json could not be parsed: {}", e))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
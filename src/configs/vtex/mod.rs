@@ -4,25 +4,27 @@
 //! ## Examples
 //! Vtex:
 //! ```rust
-//! let vtex = Vtex::new();
+//! let vtex = Vtex::info()?;
 //! ```
 //!
-//! ## Panics
-//! This function will panic if the VTEX CLI is not properly installed and if your login and authentication token are not set.
+//! ## Errors
+//! A `QuixError::Session` is returned if the VTEX CLI is not properly installed or if your login and authentication token are not set.
 //! This is because the CLI will not be able to authenticate with the VTEX API.
 //! With that further requests will not be able to be sent to the builder.
 
-use crate::utils::json;
+use crate::{
+    configs::{configstore, ConfigStore},
+    errors::QuixError,
+    utils::json,
+};
 use serde::Deserialize;
 use serde_json::Value;
 use std::{
     fs::File,
-    io::{Read, Write},
     path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use super::Project;
-
 use serde_json::json;
 
 /// # Vtex struct.
@@ -33,12 +35,14 @@ use serde_json::json;
 pub struct Vtex {
     // * Session Data
     pub account: String,
+    #[allow(dead_code)] // While this is not implemented, dead_code suppresses the warning.
     pub login: String,
     pub token: String,
 
     // * Workspace Data
     pub workspace: String,
     #[serde(alias = "_lastUsedWorkspace")]
+    #[allow(dead_code)] // While this is not implemented, dead_code suppresses the warning.
     pub last_used_workspace: String,
 }
 
@@ -47,143 +51,65 @@ pub struct Vtex {
 ///
 /// # Examples
 /// ```rust
-/// let vtex = Vtex::info();
+/// let vtex = Vtex::info()?;
 /// ```
 impl Vtex {
-    pub fn info() -> Vtex {
-        let mut vtex: Vtex = Vtex {
-            account: String::new(),
-            login: String::new(),
-            token: String::new(),
-            workspace: String::new(),
-            last_used_workspace: String::new(),
-        };
-
-        // ? Get the home directory
-        match home::home_dir() {
-            Some(path) => match get_session(path) {
-                Ok(session) => {
-                    vtex = session;
-                }
-                Err(e) => {
-                    help!("Check if the VTEX CLI is installed and if your login and authentication token are set.");
-                    error!(
-                        "We ran into an error searching for the VTEX session: {:?}",
-                        e
-                    );
-                }
-            },
-            None => {
-                help!("Wait, HOW DID YOU GET HERE??");
-                error!("No home directory found.")
-            }
-        }
-
-        vtex
+    pub fn info() -> Result<Vtex, QuixError> {
+        // ? Get the `vtex.json` path
+        get_session(configstore::vtex_path()?)
     }
 
-    pub fn raw_info() -> Result<Value, ()> {
-        // ? Get the home directory
-        match home::home_dir() {
-            Some(path) => {
-                // ? Join `home` path + `.vtex` path + `vtex.json` file
-                let path = path.join(".config/configstore/vtex.json");
-                // ? Read the file to a string
-                let mut file = File::open(path).unwrap();
-                let mut contents = String::new();
-                file.read_to_string(&mut contents).unwrap();
-                let input = contents.as_str();
-
-                Ok(serde_json::from_str(input).unwrap())
-            }
-            None => {
-                help!("Wait, HOW DID YOU GET HERE??");
-                Err(error!("No home directory found."))
-            }
-        }
+    pub fn raw_info() -> Result<Value, QuixError> {
+        ConfigStore::vtex()?
+            .read()
+            .map_err(|e| QuixError::Session(format!("Could not read the VTEX session: {}", e)))
     }
 
-    pub fn set_sticky_host(host: &str) {
-        // ? Create a new Project config.
-        let project = Project::info().unwrap();
-
-        // ? Get the home directory
-        match home::home_dir() {
-            Some(path) => {
-                // ? Join `home` path + `.vtex` path + `vtex.json` file
-                let path = path.join(".config/configstore/vtex.json");
-
-                // ? Read the file to a string
-                let mut file = File::open(&path).unwrap();
-                let mut contents = String::new();
-                file.read_to_string(&mut contents).unwrap();
-                let input = contents.as_str();
-
-                // ? Convert the string to a json object.
-                let mut vtex: Value = serde_json::from_str(input).unwrap();
-                let sticky_obj = vtex
-                    .get("apps")
-                    .and_then(|value| value.get(&project.vendor))
-                    .and_then(|value| value.get(&project.name))
-                    .and_then(|value| value.get("sticky-host"));
-
-                // ? Change the sticky host, to the new one.
-                if let Some(_sticky_host) = sticky_obj {
-                    vtex.get_mut("apps")
-                        .and_then(|value| value.get_mut(&project.vendor))
-                        .and_then(|value| value.get_mut(&project.name))
-                        .and_then(|value| value.get_mut("sticky-host"))
-                        .map(|value| Some(*value = host.into()));
-
-                    let vtex = serde_json::to_string(&vtex).unwrap();
-                    let mut file = File::create(&path).unwrap();
-                    file.write_all(vtex.as_bytes()).unwrap();
-                } else {
-                    if let Some(value) = vtex.get_mut("apps") {
-                        *value = json!({
-                            project.vendor: {
-                                project.name: {
-                                    "sticky-host": {
-                                        "stickyHost": host,
-                                        "lastUpdated": 0,
-                                    }
-                                }
-                            }
-                        })
+    /// # Set the sticky host.
+    /// Stores the sticky host of the app, along with when it was resolved (milliseconds since epoch, as the toolbelt does).
+    /// - Only this app entry changes, the other apps and the rest of the toolbelt data are kept.
+    pub fn set_sticky_host(vendor: &str, name: &str, host: &str) -> Result<(), QuixError> {
+        ConfigStore::vtex()?.update(json!({
+            "apps": {
+                vendor: {
+                    name: {
+                        "sticky-host": {
+                            "stickyHost": host,
+                            "lastUpdated": now_millis(),
+                        }
                     }
-
-                    // Write vtex to file.
-                    let vtex = serde_json::to_string(&vtex).unwrap();
-                    let mut file = File::create(&path).unwrap();
-                    file.write_all(vtex.as_bytes()).unwrap();
                 }
             }
-            None => {
-                help!("Wait, HOW DID YOU GET HERE??");
-                error!("No home directory found.")
-            }
-        }
+        }))
     }
 }
 
+/// # Now, in milliseconds.
+/// Milliseconds since the epoch, the format used by the toolbelt for the `lastUpdated` fields.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
 /// # Get the vtex data from the vtex file.
 /// This function will get the vtex from the vtex file.
 ///
-/// # Panics
-/// This function will panic if the vtex file is not found.
+/// # Errors
+/// A `QuixError::Session` if the vtex file is not found, or is missing the login data.
 /// This is because the CLI will not be able to authenticate with the VTEX API.
-pub fn get_session(path: PathBuf) -> Result<Vtex, ()> {
-    // ? Join `home` path + `.vtex` path + `vtex.json` file
-    let path = path.join(".config/configstore/vtex.json");
-
+pub fn get_session(path: PathBuf) -> Result<Vtex, QuixError> {
     // ? Tries to open the file
-    match File::open(path) {
+    match File::open(&path) {
         // * File exists
-        Ok(file) => json::read(file),
+        Ok(file) => json::read(file)
+            // !!! The session is incomplete, e.g. logged out
+            .map_err(|e| QuixError::Session(format!("The VTEX session is not valid: {}", e))),
         // !!! Wasn't able to open the file
-        Err(_) => {
-            help!("Login to your account using the VETX CLI, then try again.");
-            Err(error!("No VTEX session found."))
-        }
+        Err(_) => Err(QuixError::Session(format!(
+            "No VTEX session found in {}.",
+            path.display()
+        ))),
     }
 }
@@ -0,0 +1,45 @@
+//! # VTEX Apps API
+//! The VTEX Apps API manages the apps installed and linked in a workspace.
+//! Here it's used to unlink the apps, e.g. before a workspace is promoted.
+//!
+//! ## Endpoints
+//! - `/links/{app}`: Unlink an app from the workspace.
+//! - `/links`: Unlink every app from the workspace.
+
+use reqwest::{Client, Response};
+
+use crate::{
+    clients::vtex::REQUEST_TIMEOUT, connections::builder::send, constants::routes,
+    errors::QuixError,
+};
+
+/// # Unlink an app.
+/// This function will unlink the app (`vendor.name@version`) from the workspace.
+pub async fn unlink(
+    client: &Client,
+    account: &str,
+    workspace: &str,
+    app: &str,
+) -> Result<Response, QuixError> {
+    send(
+        client
+            .delete(routes::apps(account, workspace, &format!("links/{}", app)))
+            .timeout(REQUEST_TIMEOUT),
+    )
+    .await
+}
+
+/// # Unlink every app.
+/// This function will unlink all the apps linked in the workspace.
+pub async fn unlink_all(
+    client: &Client,
+    account: &str,
+    workspace: &str,
+) -> Result<Response, QuixError> {
+    send(
+        client
+            .delete(routes::apps(account, workspace, "links"))
+            .timeout(REQUEST_TIMEOUT),
+    )
+    .await
+}
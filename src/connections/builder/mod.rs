@@ -8,155 +8,256 @@
 //! - `/relink`: Relink the app to the builder.
 //! - `/clean`: Clean the builder cache.
 
-use std::fmt;
+use std::{fmt, io, time::Duration};
 
 use crate::{
-    clients,
-    configs::{Project, Vtex},
+    clients::vtex::{REQUEST_TIMEOUT, UPLOAD_TIMEOUT},
+    configs::{vtex::now_millis, LinkContext, Vtex},
     constants::routes,
+    errors::QuixError,
 };
 use routes::{Routes, Routes::Availability, Routes::Clean, Routes::Link, Routes::Relink};
 
 // HTTP Client
-use reqwest::{blocking::Client, Error};
-use reqwest::{
-    blocking::Response,
-    header::{HeaderMap, HeaderValue},
-};
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::{Body, Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_TYPE};
+
+/// # VTEX error.
+/// The body of the builder error responses.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VTEXError {
+    code: String,
+    message: String,
+}
+
+/// # Sticky host header.
+/// Every request to the builder goes to the instance that holds the link.
+const STICKY_HOST: &str = "x-vtex-sticky-host";
+
+/// # Send a request.
+/// Sends the request, turning the error responses into a `QuixError::Builder`.
+pub async fn send(request: RequestBuilder) -> Result<Response, QuixError> {
+    let resp = request.send().await?;
+
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+
+    let status = resp.status().as_u16();
+    let text = resp.text().await.unwrap_or_default();
+
+    // ? Not every error comes from the builder itself (e.g. a gateway), so the body may not be a `VTEXError`.
+    let error = serde_json::from_str::<VTEXError>(&text).unwrap_or(VTEXError {
+        code: status.to_string(),
+        message: text,
+    });
 
-use reqwest::header::{ACCEPT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+    Err(QuixError::Builder {
+        status,
+        code: error.code,
+        message: error.message,
+    })
+}
+
+/// # Sticky request.
+/// Pins the request to the sticky host in the context, the same client serves any host.
+fn sticky(request: RequestBuilder, ctx: &LinkContext) -> RequestBuilder {
+    match &ctx.sticky_host {
+        Some(host) => request.header(STICKY_HOST, host),
+        None => request,
+    }
+}
 
 /// # Clean the builder for the app.
 /// This function will clean the builder for the app.
-pub fn clean(client: &Client) -> Result<Response, Error> {
-    client // Setup the request.
-        .post(&Routes::assemble(Clean)) // Define the endpoint.
-        .header(ACCEPT_ENCODING, "gzip") // More headers.
-        .header(CONTENT_TYPE, "application/json") // Guess what.
-        .body(r#"{"headers": {"Content-Type": "application/json"},"metric": "bh-clean"}"#) // And finally the body.
-        .send() // Just wrap it up and send it.
+pub async fn clean(client: &Client, ctx: &LinkContext) -> Result<Response, QuixError> {
+    send(
+        sticky(client.post(Routes::assemble(Clean, ctx)), ctx) // Setup the request, and define the endpoint.
+            .timeout(REQUEST_TIMEOUT) // Don't hang the session.
+            .header(ACCEPT_ENCODING, "gzip") // More headers.
+            .header(CONTENT_TYPE, "application/json") // Guess what.
+            .body(r#"{"headers": {"Content-Type": "application/json"},"metric": "bh-clean"}"#), // And finally the body.
+    )
+    .await // Just wrap it up and send it.
 }
 
 /// # Link to the builder.
 /// This function will link the app to the builder.
-pub fn link(client: &Client, file: Vec<u8>) -> Result<Response, Error> {
-    client // Setup the request.
-        .post(&Routes::assemble(Link)) // Define the endpoint.
-        .header(ACCEPT_ENCODING, "gzip") // More headers.
-        .header(CONTENT_TYPE, "application/octet-stream") // Guess what.
-        .header(CONTENT_LENGTH, file.len()) // And more headers.
-        .body(file) // And finally the body.
-        .send() // Just wrap it up and send it.
+/// - The bundle is streamed (chunked), so the upload starts before the whole zip is ready.
+pub async fn link<S>(client: &Client, ctx: &LinkContext, bundle: S) -> Result<Response, QuixError>
+where
+    S: Stream<Item = io::Result<Bytes>> + Send + Sync + 'static,
+{
+    send(
+        sticky(client.post(Routes::assemble(Link, ctx)), ctx) // Setup the request, and define the endpoint.
+            .timeout(UPLOAD_TIMEOUT) // Don't hang the session.
+            .header(ACCEPT_ENCODING, "gzip") // More headers.
+            .header(CONTENT_TYPE, "application/octet-stream") // Guess what.
+            .body(Body::wrap_stream(bundle)), // And finally the body, without a length as it's still being zipped.
+    )
+    .await // Just wrap it up and send it.
 }
 
-#[derive(Debug)]
-pub struct RelinkBody {
-    pub content: String,
+/// # Relink file.
+/// A single changed file, as expected by the builder.
+/// - A removed file is sent without content (`null`), so the builder deletes it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkFile {
+    pub content: Option<String>,
     pub byte_size: usize,
     pub path: String,
 }
+
+/// # Relink body.
+/// All the files changed during a debounce window, sent in a single relink request.
+/// - It's displayed as the JSON array the builder expects.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct RelinkBody {
+    pub files: Vec<RelinkFile>,
+}
 impl fmt::Display for RelinkBody {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "[{{\"content\":\"{}\",\"byteSize\":{},\"path\":\"{}\"}}]",
-            self.content, self.byte_size, self.path
-        )
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
     }
 }
 
 /// # Re-Link to the builder.
 /// This function will relink the app to the builder.
-pub fn relink(client: &Client, body: RelinkBody) -> Result<Response, Error> {
-    // Create a new HTTP blocking client.
-    client // Setup the request.
-        .put(&Routes::assemble(Relink)) // Define the endpoint.
-        .header(ACCEPT_ENCODING, "gzip") // More headers.
-        .header(CONTENT_TYPE, "application/json") // Guess what.
-        .body(RelinkBody::to_string(&body)) // And finally the body.
-        .send() // Just wrap it up and send it.
+pub async fn relink(
+    client: &Client,
+    ctx: &LinkContext,
+    body: &RelinkBody,
+) -> Result<Response, QuixError> {
+    send(
+        sticky(client.put(Routes::assemble(Relink, ctx)), ctx) // Setup the request, and define the endpoint.
+            .timeout(REQUEST_TIMEOUT) // Don't hang the session.
+            .header(ACCEPT_ENCODING, "gzip") // More headers.
+            .header(CONTENT_TYPE, "application/json") // Guess what.
+            .body(body.to_string()), // And finally the body.
+    )
+    .await // Just wrap it up and send it.
 }
 
 /// # Check the availability of the builder.
-/// This function will check the availability of the builder.
-/// - If the builder is up to date, it will return the new client.
-/// - If the builder is not up to date, it will return the old client.
-pub fn check_availability() -> Result<Client, Error> {
-    // ? Create a new Project config struct.
-    let project = Project::info().unwrap();
-
-    // ? Instantiate a user session.
-    let session = Vtex::info();
-
-    let binding = Vtex::raw_info().unwrap();
+/// This function will check the availability of the builder, and resolve the sticky host of the app.
+/// - A stored sticky host younger than `max_age` is used as is, skipping the availability request.
+/// - With a zero `max_age`, the availability request always runs.
+/// - The sticky host in use is kept in the context, the next requests are pinned to it.
+pub async fn check_availability(
+    client: &Client,
+    ctx: &mut LinkContext,
+    max_age: Duration,
+) -> Result<(), QuixError> {
+    let binding = Vtex::raw_info()?;
     let sticky_obj = binding
         .get("apps")
-        .and_then(|value| value.get(&project.vendor))
-        .and_then(|value| value.get(&project.name))
+        .and_then(|value| value.get(&ctx.vendor))
+        .and_then(|value| value.get(&ctx.name))
         .and_then(|value| value.get("sticky-host"));
 
     let mut sticky_host = String::from("");
 
-    if let Some(sticky_obj) = sticky_obj {
-        sticky_host = sticky_obj.get("stickyHost").unwrap().to_string()
+    if let Some(sticky_obj) = sticky_obj.filter(|obj| is_fresh(obj, max_age)) {
+        sticky_host = sticky_obj
+            .get("stickyHost")
+            .and_then(|host| host.as_str())
+            .unwrap_or_default()
+            .to_string()
     } else {
         // make a post request to `/0/availability/vendor.app@version` to get the sticky host, and store it in the config, the sticky host comes in the resp headers as `x-vtex-sticky-host`
 
-        // ? Create a new VTEX Client.
-        let client = clients::vtex::new(&session.token);
-
-        // ? Create the headers to identify the availability request.
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "x-vtex-sticky-host",
-            HeaderValue::from_str(&format!(
-                "request:{}:{}:{}.{}@{}",
-                &session.account,
-                &session.workspace,
-                &project.vendor,
-                &project.name,
-                &project.version
-            ))
-            .unwrap(),
-        );
-
-        let resp = client
-            .post(&Routes::assemble(Availability))
-            .headers(headers)
-            .send()
-            .unwrap();
-
-        match resp.headers().get("x-vtex-sticky-host") {
-            Some(host) => {
-                let host = host.to_str().unwrap();
-                let host = host.split(':').collect::<Vec<&str>>();
-                let host = host[0];
-                let host = host.to_string();
-
-                Vtex::set_sticky_host(host.as_str());
-
-                sticky_host = host
-            }
-            None => {
-                help!("Error during the availability check. Have you set the correct account and workspace?");
-                trace!("{}", &resp.text().unwrap());
-                error!("Could not get the sticky host from the VTEX API.");
+        // ? The header identifies the availability request.
+        let request = format!("request:{}:{}:{}", &ctx.account, &ctx.workspace, ctx.app());
+
+        let resp = send(
+            client
+                .post(Routes::assemble(Availability, ctx))
+                .timeout(REQUEST_TIMEOUT)
+                .header(STICKY_HOST, request),
+        )
+        .await?;
+
+        // ? The host comes as `host:port`, only the host is used.
+        if let Some(host) = resp
+            .headers()
+            .get(STICKY_HOST)
+            .and_then(|host| host.to_str().ok())
+            .and_then(|host| host.split(':').next())
+        {
+            if let Err(e) = Vtex::set_sticky_host(&ctx.vendor, &ctx.name, host) {
+                trace!("Could not store the sticky host: {}", e);
             }
+
+            sticky_host = host.to_string()
         }
     }
 
-    // ? Create a new VTEX Client with the Sticky Host.
-    let mut headers = HeaderMap::new();
+    // ? The sticky host has to be a valid header, as every request carries it.
+    if sticky_host.is_empty() || reqwest::header::HeaderValue::from_str(&sticky_host).is_err() {
+        return Err(QuixError::Builder {
+            status: 0,
+            code: "no_sticky_host".to_string(),
+            message: "Could not get the sticky host from the VTEX API.".to_string(),
+        });
+    }
+
+    ctx.sticky_host = Some(sticky_host);
+
+    Ok(())
+}
 
-    if sticky_host.is_empty() {
-        help!("Error reading the sticky host. Have you set the correct account and workspace?");
-        error!("Could not get the sticky host from the VTEX API.");
+/// # Is fresh.
+/// Tells if a stored sticky host is younger than `max_age`, based on its `lastUpdated` field.
+fn is_fresh(sticky_obj: &Value, max_age: Duration) -> bool {
+    let last_updated = sticky_obj
+        .get("lastUpdated")
+        .and_then(|value| value.as_u64())
+        .unwrap_or_default();
+    let age = Duration::from_millis(now_millis().saturating_sub(last_updated));
+
+    if age < max_age {
+        true
     } else {
-        headers.insert(
-            "x-vtex-sticky-host",
-            HeaderValue::from_str(&sticky_host).unwrap(),
-        );
+        if !max_age.is_zero() {
+            trace!("⌛ The stored sticky host expired, resolving a new one.");
+        }
+        false
     }
+}
 
-    Ok(clients::vtex::new_with_headers(&session.token, &headers))
+/// # Host moved.
+/// Tells if a failed request looks like the sticky host is gone, and it's worth resolving it again.
+/// - Connection errors and timeouts, the instance is unreachable.
+/// - Gateway errors, the router has nowhere to send the request.
+pub fn host_moved<T>(result: &Result<T, QuixError>) -> bool {
+    match result {
+        // * Bad gateway, service unavailable and gateway timeout.
+        Err(QuixError::Builder { status, .. }) => matches!(status, 502..=504),
+        Err(QuixError::Network(e)) => e.is_connect() || e.is_timeout(),
+        _ => false,
+    }
+}
+
+/// # Resolve the sticky host again.
+/// Runs the availability request, ignoring the stored host, and pins the context to the new one.
+/// - It will return `true` if the context is ready to retry the request.
+pub async fn reresolve(client: &Client, ctx: &mut LinkContext) -> bool {
+    warn!("The builder instance seems to have moved, resolving it again... 🧭");
+
+    match check_availability(client, ctx, Duration::ZERO).await {
+        Ok(()) => true,
+        Err(e) => {
+            trace!("Could not resolve the builder again: {}", e);
+            false
+        }
+    }
 }
@@ -0,0 +1,212 @@
+//! # Builder-hub events
+//! The events the builder-hub sends through the Colossus, one model for each subject.
+//! So each one can be handled and rendered on its own, instead of matching on the raw data.
+//!
+//! ## Subjects
+//! - `build.status`: The build started (with the builders running), succeeded (with the builder timings) or failed (with the builder errors).
+//! - `receive.status`: The builder received the files, or failed to.
+//!
+//! # Examples
+//! ```rust
+//! match ColossusEvent::<BuildStatus>::parse(&event.data) {
+//!     Some(ColossusEvent { body: BuildStatus::Success { details }, .. }) => {}
+//!     _ => {}
+//! }
+//! ```
+//!
+//! # Unknown payloads
+//! A payload that doesn't match its subject parses to `None`, and an unknown `code` to the `Unknown` variant, so nothing panics.
+
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::output::Record;
+
+/// # Colossus event.
+/// The envelope of every event, `B` being the body of its subject.
+#[derive(Debug, Deserialize)]
+pub struct ColossusEvent<B> {
+    pub subject: Option<String>, // The app the event is about, `vendor.name@version`
+    pub body: B,
+}
+
+/// # Implements the `ColossusEvent` and define the **parse** method.
+impl<B: DeserializeOwned> ColossusEvent<B> {
+    /// # Parse an event.
+    /// It will return `None` if the data is not an event of this subject.
+    pub fn parse(data: &str) -> Option<ColossusEvent<B>> {
+        serde_json::from_str(data).ok()
+    }
+}
+
+/// # Build status.
+/// The `build.status` body, tagged by its `code`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum BuildStatus {
+    Start {
+        #[serde(default)]
+        details: BuildStart,
+    },
+    Success {
+        #[serde(default)]
+        details: BuildSuccess,
+    },
+    Fail {
+        #[serde(default)]
+        details: BuildFailure,
+    },
+    InitialLinkRequired, // The builder lost the link, it needs the whole bundle again
+    GenericError {
+        message: Option<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// # Implements the `BuildStatus` and define the **record** method.
+impl BuildStatus {
+    /// # Record
+    /// The status of the `app` build as an output record, `None` if it's unknown.
+    pub fn record(&self, app: Option<&str>) -> Option<Record> {
+        let (status, builders, timings, message, errors) = match self {
+            BuildStatus::Start { details } => (
+                "start",
+                details.builders.clone(),
+                BTreeMap::new(),
+                None,
+                vec![],
+            ),
+            BuildStatus::Success { details } => {
+                ("success", vec![], details.timings.clone(), None, vec![])
+            }
+            BuildStatus::Fail { details } => (
+                "fail",
+                vec![],
+                BTreeMap::new(),
+                details.message.clone(),
+                details.errors.iter().map(|e| e.to_string()).collect(),
+            ),
+            BuildStatus::InitialLinkRequired => (
+                "initial_link_required",
+                vec![],
+                BTreeMap::new(),
+                None,
+                vec![],
+            ),
+            BuildStatus::GenericError { message } => (
+                "generic_error",
+                vec![],
+                BTreeMap::new(),
+                message.clone(),
+                vec![],
+            ),
+            BuildStatus::Unknown => return None,
+        };
+
+        Some(Record::Build {
+            status: status.to_string(),
+            app: app.map(String::from),
+            builders,
+            timings,
+            message,
+            errors,
+        })
+    }
+}
+
+/// # Build start.
+/// Which builders are running, every one in the manifest when empty.
+#[derive(Debug, Default, Deserialize)]
+pub struct BuildStart {
+    #[serde(default)]
+    pub builders: Vec<String>,
+}
+
+/// # Build success.
+/// How long each builder took.
+#[derive(Debug, Default, Deserialize)]
+pub struct BuildSuccess {
+    #[serde(default)]
+    pub timings: BTreeMap<String, u64>, // Builder name => milliseconds
+}
+
+/// # Implements the `BuildSuccess` and define the **timings** method.
+impl BuildSuccess {
+    /// # Timings
+    /// The builder timings, as durations.
+    pub fn timings(&self) -> Vec<(String, Duration)> {
+        self.timings
+            .iter()
+            .map(|(builder, ms)| (builder.clone(), Duration::from_millis(*ms)))
+            .collect()
+    }
+}
+
+/// # Build failure.
+/// What went wrong, for each builder.
+#[derive(Debug, Default, Deserialize)]
+pub struct BuildFailure {
+    pub message: Option<String>,
+    #[serde(default)]
+    pub errors: Vec<BuilderError>,
+}
+
+/// # Builder error.
+/// A single error reported by a builder, pointing at the file when it can.
+#[derive(Debug, Deserialize)]
+pub struct BuilderError {
+    pub builder: Option<String>, // e.g. `react`
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(builder) = &self.builder {
+            write!(f, "[{}] ", builder)?;
+        }
+        write!(f, "{}", self.message)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " ({}:{})", file, line),
+            (Some(file), None) => write!(f, " ({})", file),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// # Receive status.
+/// The `receive.status` body, tagged by its `code`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum ReceiveStatus {
+    Start,
+    Success,
+    Fail {
+        message: Option<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// # Implements the `ReceiveStatus` and define the **record** method.
+impl ReceiveStatus {
+    /// # Record
+    /// The status of the `app` files as an output record, `None` if it's unknown.
+    pub fn record(&self, app: Option<&str>) -> Option<Record> {
+        let (status, message) = match self {
+            ReceiveStatus::Start => ("start", None),
+            ReceiveStatus::Success => ("success", None),
+            ReceiveStatus::Fail { message } => ("fail", message.clone()),
+            ReceiveStatus::Unknown => return None,
+        };
+
+        Some(Record::Receive {
+            status: status.to_string(),
+            app: app.map(String::from),
+            message,
+        })
+    }
+}
@@ -0,0 +1,214 @@
+//! # App log filter
+//! Picks the app logs worth showing during a link, so a single service can be followed without the noise of the workspace.
+//! - By app (`vendor.name`, any version, or `vendor.name@version`) and by vendor.
+//! - By level, the least important one shown.
+//! - By regex, the lines have to match an `include` (if any), and can't match an `exclude`.
+//!
+//! The `include` matches are highlighted in the lines shown.
+//!
+//! # Examples
+//! ```rust
+//! let filter = LogFilter::new(settings.logs)?;
+//! filter.matches(Some("vendor.app@1.0.0"), Level::Warn, "Boom"); // true
+//! ```
+
+use colored::Colorize;
+use regex::Regex;
+
+use crate::{configs::settings::LogSettings, errors::QuixError, output::Level};
+
+/// # LogFilter struct.
+/// The compiled filters, an empty one lets everything through.
+#[derive(Debug, Default, Clone)]
+pub struct LogFilter {
+    apps: Vec<String>,
+    vendors: Vec<String>,
+    level: Option<Level>,
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+/// # Implements the `LogFilter` and define the **new**, **is_empty**, **matches** and **highlight** methods.
+impl LogFilter {
+    /// # New filter.
+    /// Compiles the filters in the settings.
+    ///
+    /// # Errors
+    /// A `QuixError::Config` if a level or a regex is not valid.
+    pub fn new(settings: LogSettings) -> Result<LogFilter, QuixError> {
+        let level = settings
+            .level
+            .map(|level| level.parse::<Level>())
+            .transpose()
+            .map_err(|e| QuixError::Config(format!("Invalid log level filter: {}", e)))?;
+
+        Ok(LogFilter {
+            apps: settings.apps,
+            vendors: settings.vendors,
+            level,
+            include: compile(settings.include)?,
+            exclude: compile(settings.exclude)?,
+        })
+    }
+
+    /// # Is empty
+    /// Tells if every line goes through.
+    pub fn is_empty(&self) -> bool {
+        self.apps.is_empty()
+            && self.vendors.is_empty()
+            && self.level.is_none()
+            && self.include.is_empty()
+            && self.exclude.is_empty()
+    }
+
+    /// # Matches
+    /// Tells if a line of `app` (`vendor.name@version`, if known) is shown.
+    pub fn matches(&self, app: Option<&str>, level: Level, message: &str) -> bool {
+        // ? An unknown app only goes through when no app is asked for.
+        let app_matches = match app {
+            _ if self.apps.is_empty() && self.vendors.is_empty() => true,
+            Some(app) => {
+                self.apps
+                    .iter()
+                    .any(|a| app == a || app.starts_with(&format!("{}@", a)))
+                    || self
+                        .vendors
+                        .iter()
+                        .any(|v| app.starts_with(&format!("{}.", v)))
+            }
+            None => false,
+        };
+
+        app_matches
+            && self.level.is_none_or(|max| level <= max)
+            && (self.include.is_empty() || self.include.iter().any(|r| r.is_match(message)))
+            && !self.exclude.iter().any(|r| r.is_match(message))
+    }
+
+    /// # Highlight
+    /// Marks the `include` matches in the line.
+    pub fn highlight(&self, message: &str) -> String {
+        self.include
+            .iter()
+            .fold(message.to_string(), |line, regex| {
+                regex
+                    .replace_all(&line, |m: &regex::Captures| {
+                        m[0].black().on_yellow().to_string()
+                    })
+                    .into_owned()
+            })
+    }
+}
+
+/// # Compile the regexes.
+fn compile(patterns: Vec<String>) -> Result<Vec<Regex>, QuixError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern)
+                .map_err(|e| QuixError::Config(format!("Invalid log filter `{}`: {}", pattern, e)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(settings: LogSettings) -> LogFilter {
+        LogFilter::new(settings).unwrap()
+    }
+
+    #[test]
+    fn lets_everything_through_when_empty() {
+        let filter = filter(LogSettings::default());
+
+        assert!(filter.is_empty());
+        assert!(filter.matches(None, Level::Trace, "Anything"));
+        assert!(filter.matches(Some("vendor.app@1.0.0"), Level::Error, "Boom"));
+    }
+
+    #[test]
+    fn matches_the_apps_with_any_version() {
+        let filter = filter(LogSettings {
+            apps: vec!["vendor.app".to_string(), "other.app@2.0.0".to_string()],
+            ..Default::default()
+        });
+
+        assert!(filter.matches(Some("vendor.app@1.0.0"), Level::Info, ""));
+        assert!(filter.matches(Some("vendor.app"), Level::Info, ""));
+        assert!(filter.matches(Some("other.app@2.0.0"), Level::Info, ""));
+        assert!(!filter.matches(Some("other.app@2.1.0"), Level::Info, ""));
+        assert!(!filter.matches(Some("vendor.application@1.0.0"), Level::Info, ""));
+        assert!(!filter.matches(None, Level::Info, ""));
+    }
+
+    #[test]
+    fn matches_the_apps_of_a_vendor() {
+        let filter = filter(LogSettings {
+            vendors: vec!["vendor".to_string()],
+            ..Default::default()
+        });
+
+        assert!(filter.matches(Some("vendor.app@1.0.0"), Level::Info, ""));
+        assert!(!filter.matches(Some("vendorx.app@1.0.0"), Level::Info, ""));
+        assert!(!filter.matches(None, Level::Info, ""));
+    }
+
+    #[test]
+    fn drops_the_less_important_levels() {
+        let filter = filter(LogSettings {
+            level: Some("warn".to_string()),
+            ..Default::default()
+        });
+
+        assert!(filter.matches(None, Level::Error, ""));
+        assert!(filter.matches(None, Level::Warn, ""));
+        assert!(!filter.matches(None, Level::Info, ""));
+        assert!(!filter.matches(None, Level::Debug, ""));
+    }
+
+    #[test]
+    fn includes_and_excludes_by_regex() {
+        let filter = filter(LogSettings {
+            include: vec!["^GET".to_string(), "timeout".to_string()],
+            exclude: vec!["/healthcheck".to_string()],
+            ..Default::default()
+        });
+
+        assert!(filter.matches(None, Level::Info, "GET /products"));
+        assert!(filter.matches(None, Level::Info, "Request timeout"));
+        assert!(!filter.matches(None, Level::Info, "POST /products"));
+        assert!(!filter.matches(None, Level::Info, "GET /healthcheck"));
+    }
+
+    #[test]
+    fn rejects_the_invalid_settings() {
+        let level = LogFilter::new(LogSettings {
+            level: Some("loud".to_string()),
+            ..Default::default()
+        });
+        let regex = LogFilter::new(LogSettings {
+            include: vec!["(".to_string()],
+            ..Default::default()
+        });
+
+        assert!(matches!(level, Err(QuixError::Config(_))));
+        assert!(matches!(regex, Err(QuixError::Config(_))));
+    }
+
+    #[test]
+    fn highlights_the_included_matches() {
+        colored::control::set_override(true);
+        let filter = filter(LogSettings {
+            include: vec!["time\\w+".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            filter.highlight("Request timeout"),
+            format!("Request {}", "timeout".black().on_yellow())
+        );
+        assert_eq!(LogFilter::default().highlight("Boom"), "Boom");
+    }
+}
@@ -5,190 +5,492 @@
 //! ## Endpoints
 //! - `/events`: Receive events from the VTEX platform.
 //! - `/logs`: Receive logs from your app.
+//!
+//! ## Reconnection
+//! The streams are kept alive for the whole link session, when one drops it reconnects with an exponential backoff (plus jitter),
+//! resuming from the last received event when the server provides an ID.
+//!
+//! ## Multiplexing
+//! The three streams run concurrently in a single task, sharing the session client (and its connections).
+//! When several apps are linked, they share the streams too, each event is routed by its `subject` (the app).
+//! - The build and status events of the apps outside the session are skipped.
+//! - The app logs are tagged with their app, e.g. `[vendor.app] Started`.
+//!
+//! ## Filters
+//! The app logs can be filtered by app, vendor, level and regex (see `filter`), the build and status events never are.
+//!
+//! ## Progress
+//! The build events drive a live view of the builders running (a line per app building), any log line clears it first (see `output`).
 
-use std::{path::PathBuf, thread};
-
-// * Eventsource for the CLI.
-use eventsource::{
-    event::Event,
-    reqwest::{Client as EventSourceClient, Error},
+use std::{
+    collections::{hash_map::RandomState, BTreeSet},
+    hash::BuildHasher,
+    mem,
+    sync::Mutex,
+    time::{Duration, SystemTime},
 };
 
-use reqwest::blocking::Client;
+// * Eventsource parser for the CLI.
+use eventsource::event::{parse_event_line, Event, ParseResult};
+
+use reqwest::{header::ACCEPT, Client, Response, Url};
 use serde::Deserialize;
+use tokio::{
+    sync::{mpsc::UnboundedSender, Notify},
+    time,
+};
 
-use crate::{clients, commands::link::send_package, configs::Vtex};
+use crate::{
+    configs::LinkContext,
+    constants::routes,
+    errors::QuixError,
+    output::{self, Level, Record},
+    utils::progress::Progress,
+};
+
+pub mod events;
+use events::{BuildStatus, ColossusEvent, ReceiveStatus};
+
+pub mod filter;
+use filter::LogFilter;
+
+/// # Backoff base.
+/// Delay before the first reconnection, the server can change it with a `retry` field.
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
 
+/// # Backoff limit.
+/// The delay doubles on each failed reconnection, up to this.
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// # Tick interval.
+/// How often the build progress is redrawn.
+const TICK: Duration = Duration::from_millis(100);
+
+/// # Stream signal.
+/// What the streams ask from (or tell) the link session, as they can't act on it themselves.
+/// - The apps are named `vendor.name`, `None` when the builder didn't tell which one.
+#[derive(Debug)]
+pub enum Signal {
+    InitialLinkRequired(Option<String>), // The builder lost the link of the app, the whole bundle has to be sent again.
+    Listening, // The build stream is connected, no build that ends from now on is missed.
+    Built { app: Option<String>, success: bool }, // The build of the app ended.
+    Interrupted, // The Colossus ended the link session.
+}
+
+/// # Log event.
+/// A loose model for the app logs, and for the events no subject model knows.
 #[derive(Deserialize)]
-struct ColossusEvent {
-    body: ColossusBody,
+struct LogEvent {
+    body: LogBody,
     level: Option<String>,
+    subject: Option<String>, // The app that logged, e.g. `vendor.app@1.0.0`
+    sender: Option<String>,
 }
 
 #[derive(Deserialize)]
-struct ColossusBody {
+struct LogBody {
     level: Option<String>,
     msg: Option<String>,
     message: Option<String>,
 }
 
-fn build(account: &str, workspace: &str, t_client: Client, it_path: PathBuf, it_client: Client) {
-    let log_url = reqwest::Url::parse(&format!("https://infra.io.vtex.com/colossus/v0/{}/{}/events?onUnsubscribe=link_interrupted&sender=vtex.builder-hub&keys=build.status", account, workspace)).unwrap();
+/// # Parse a stream URL.
+/// The base URL is configurable, so it may not be a valid one.
+fn parse_url(url: &str) -> Result<Url, QuixError> {
+    Url::parse(url).map_err(|e| QuixError::Colossus(format!("Invalid stream URL {}: {}", url, e)))
+}
+
+async fn build(
+    ctx: &LinkContext,
+    apps: &[String],
+    client: &Client,
+    signals: UnboundedSender<Signal>,
+    progress: &Mutex<Progress>,
+    building: &Notify,
+) -> Result<(), QuixError> {
+    let log_url = parse_url(&routes::colossus(
+        ctx,
+        "events?onUnsubscribe=link_interrupted&sender=vtex.builder-hub&keys=build.status",
+    ))?;
 
-    let t_client = EventSourceClient::new_with_client(log_url, t_client);
+    // ? The session may be gone already, then nobody is listening to the signals.
+    let listening = || {
+        let _ = signals.send(Signal::Listening);
+    };
 
-    for event in t_client {
-        match event {
-            Ok(event) => {
-                if event.data == "link_interrupted" {
-                    error!("Link interrupted.");
-                } else if event.data != "ping\n" {
-                    if event.data.contains("initial_link_required") {
-                        send_package(&it_path, &it_client);
-                    } else if event.data.contains("generic_error") {
-                        let message = serde_json::from_str::<ColossusEvent>(&event.data)
-                            .unwrap()
-                            .body
-                            .message
-                            .unwrap();
+    subscribe("build", log_url, client, Some(&listening), |event| {
+        let mut progress = progress.lock().unwrap();
+
+        // ? The parser ends the data with a newline, like the `ping\n` below.
+        if event.data.trim_end() == "link_interrupted" {
+            progress.stop();
+            error!("Link interrupted.");
+            let _ = signals.send(Signal::Interrupted);
+            output::emit(Record::Build {
+                status: "interrupted".to_string(),
+                app: None,
+                builders: vec![],
+                timings: Default::default(),
+                message: None,
+                errors: vec![],
+            });
+        } else if event.data != "ping\n" {
+            let parsed = ColossusEvent::<BuildStatus>::parse(&event.data);
+            let Some(app) = owner(parsed.as_ref().and_then(|e| e.subject.as_deref()), apps) else {
+                return;
+            };
+
+            let status = parsed.map(|e| e.body);
+            if let Some(record) = status.as_ref().and_then(|s| s.record(app.as_deref())) {
+                output::emit(record);
+            }
 
-                        warn!("Generic error: {}", message);
+            // ? The build is labeled with its app, when there's more than one.
+            let label = app.clone().filter(|_| apps.len() > 1);
 
-                        drop(message)
-                    } else {
-                        match_event(Ok(event));
+            match status {
+                Some(BuildStatus::Start { details }) => {
+                    progress.start(label, details.builders);
+                    if progress.ticking() {
+                        building.notify_one();
                     }
                 }
-            }
-            Err(e) => {
-                error!("Error: {}", e);
+                Some(BuildStatus::Success { details }) => {
+                    progress.finish(label, details.timings());
+                    let _ = signals.send(Signal::Built { app, success: true });
+                }
+                Some(BuildStatus::Fail { details }) => {
+                    let _ = signals.send(Signal::Built {
+                        app,
+                        success: false,
+                    });
+                    let took = progress.fail(label.clone()).unwrap_or_default();
+                    error!(
+                        "❌ {}Build failed after {:.1}s: {}",
+                        label.map(|app| format!("[{}] ", app)).unwrap_or_default(),
+                        took.as_secs_f64(),
+                        details
+                            .message
+                            .as_deref()
+                            .unwrap_or("see the errors below.")
+                    );
+                    for e in &details.errors {
+                        error!("{}", e);
+                    }
+                }
+                Some(BuildStatus::InitialLinkRequired) => {
+                    let _ = signals.send(Signal::InitialLinkRequired(app));
+                }
+                Some(BuildStatus::GenericError { message }) => {
+                    warn!("Generic error: {}", message.unwrap_or_default())
+                }
+                // ? Not a build status we know, rendered like a log.
+                Some(BuildStatus::Unknown) | None => match_event(event, &LogFilter::default()),
             }
         }
-    }
+    })
+    .await;
+
+    Ok(())
 }
 
-fn status(account: &str, workspace: &str, t_client: Client) {
-    let log_url = reqwest::Url::parse(&format!("https://infra.io.vtex.com/colossus/v0/{}/{}/events?onUnsubscribe=link_interrupted&sender=vtex.builder-hub&keys=receive.status", account, workspace)).unwrap();
+async fn status(ctx: &LinkContext, apps: &[String], client: &Client) -> Result<(), QuixError> {
+    let log_url = parse_url(&routes::colossus(
+        ctx,
+        "events?onUnsubscribe=link_interrupted&sender=vtex.builder-hub&keys=receive.status",
+    ))?;
 
-    let t_client = EventSourceClient::new_with_client(log_url, t_client);
+    subscribe("status", log_url, client, None, |event| {
+        let parsed = ColossusEvent::<ReceiveStatus>::parse(&event.data);
+        let Some(app) = owner(parsed.as_ref().and_then(|e| e.subject.as_deref()), apps) else {
+            return;
+        };
 
-    for event in t_client {
-        match_event(event);
-    }
+        let status = parsed.map(|e| e.body);
+        if let Some(record) = status.as_ref().and_then(|s| s.record(app.as_deref())) {
+            output::emit(record);
+        }
+
+        match status {
+            Some(ReceiveStatus::Start) => debug!("📥 The builder is receiving the files..."),
+            Some(ReceiveStatus::Success) => debug!("📥 The builder received the files."),
+            Some(ReceiveStatus::Fail { message }) => error!(
+                "The builder could not receive the files: {}",
+                message.unwrap_or_default()
+            ),
+            // ? Not a receive status we know, rendered like a log.
+            Some(ReceiveStatus::Unknown) | None => match_event(event, &LogFilter::default()),
+        }
+    })
+    .await;
+
+    Ok(())
 }
 
-fn logs(account: &str, workspace: &str, t_client: Client) {
-    let log_url = reqwest::Url::parse(&format!(
-        "https://infra.io.vtex.com/colossus/v0/{}/{}/logs?level=debug",
-        account, workspace
-    ))
-    .unwrap();
+async fn logs(ctx: &LinkContext, client: &Client, filter: &LogFilter) -> Result<(), QuixError> {
+    // ? Only the lines up to the threshold are streamed.
+    let log_url = parse_url(&routes::colossus(
+        ctx,
+        &format!("logs?level={}", output::threshold().colossus()),
+    ))?;
 
-    let t_client = EventSourceClient::new_with_client(log_url, t_client);
+    subscribe("logs", log_url, client, None, |event| {
+        match_event(event, filter)
+    })
+    .await;
 
-    for event in t_client {
-        match_event(event);
-    }
+    Ok(())
 }
 
-pub fn stream(it_path: PathBuf, it_client: Client) {
-    // Make a vector to hold the children which are spawned.
-    let mut children = vec![];
+/// # App name.
+/// The app without its version, `vendor.app@1.0.0` => `vendor.app`.
+fn app_name(app: &str) -> &str {
+    app.split('@').next().unwrap_or(app)
+}
 
-    // ? Instantiate a user session.
-    let session = Vtex::info();
+/// # Owner of an event.
+/// The app (`vendor.name`) an event with this `subject` is about, `Some(None)` if it doesn't tell.
+/// - It will return `None` for the apps outside the session, e.g. another developer's link in the workspace.
+fn owner(subject: Option<&str>, apps: &[String]) -> Option<Option<String>> {
+    match subject.map(app_name) {
+        Some(app) if apps.iter().any(|a| a == app) => Some(Some(app.to_string())),
+        Some(_) => None,
+        None => Some(None),
+    }
+}
 
-    let s1 = session.clone();
-    children.push(thread::spawn(move || {
-        // ? Create a new VTEX Client.
-        let t_client = clients::vtex::new(&s1.token);
-        build(
-            s1.account.as_str(),
-            s1.workspace.as_str(),
-            t_client,
-            it_path,
-            it_client,
-        );
-    }));
-
-    let s2 = session.clone();
-    children.push(thread::spawn(move || {
-        // ? Create a new VTEX Client.
-        let t_client = clients::vtex::new(&s2.token);
-        status(s2.account.as_str(), s2.workspace.as_str(), t_client);
-    }));
-
-    let s3 = session;
-    children.push(thread::spawn(move || {
-        // ? Create a new VTEX Client.
-        let t_client = clients::vtex::new(&s3.token);
-        logs(s3.account.as_str(), s3.workspace.as_str(), t_client);
-    }));
-
-    for child in children {
-        child.join().unwrap()
+/// # Tick the progress.
+/// Redraws the build progress while a build is in progress on a terminal.
+/// - Between builds (or off a terminal), it sleeps until `building` wakes it up.
+async fn tick(progress: &Mutex<Progress>, building: &Notify) -> Result<(), QuixError> {
+    loop {
+        if !progress.lock().unwrap().ticking() {
+            building.notified().await;
+            continue;
+        }
+
+        time::sleep(TICK).await;
+        progress.lock().unwrap().tick();
     }
 }
 
-fn match_event(event: Result<Event, Error>) {
-    match event {
-        Ok(event) => {
-            if event.data != "ping\n" {
-                let data = serde_json::from_str::<ColossusEvent>(&event.data).unwrap();
-
-                let body = data.body;
-
-                let level = match data.level {
-                    Some(level) => level,
-                    None => match body.level {
-                        Some(level) => level,
-                        None => "info".to_string(),
-                    },
-                };
-
-                if level == "info" {
-                    match body.message {
-                        Some(message) => info!("{}", message),
-                        None => {
-                            if let Some(message) = body.msg {
-                                info!("{}", message)
-                            }
-                        }
-                    }
-                } else if level == "warning" {
-                    match body.message {
-                        Some(message) => warn!("{}", message),
-                        None => {
-                            if let Some(message) = body.msg {
-                                warn!("{}", message)
-                            }
+/// # Subscribe to a stream.
+/// Reads the events from the stream, handing each one to `on_event`, for as long as the session lasts.
+/// - `on_connect` is called each time the stream is (re)connected.
+/// - When the stream drops (network blips, laptop sleep, server timeouts), it reconnects after a backoff.
+/// - The last event ID is sent back as `Last-Event-ID`, so the server can resume from it.
+async fn subscribe(
+    name: &str,
+    url: Url,
+    client: &Client,
+    on_connect: Option<&(dyn Fn() + Sync)>,
+    mut on_event: impl FnMut(Event),
+) {
+    let mut last_event_id: Option<String> = None;
+    let mut base = BACKOFF_BASE; // Backoff base, the server can change it.
+    let mut failures = 0; // Connections in a row that failed, or dropped before any event.
+    let mut dropped = false; // Whether the stream was up before.
+
+    loop {
+        match connect(&url, client, last_event_id.as_deref()).await {
+            Ok(mut resp) => {
+                if dropped {
+                    success!("🔌 Reconnected to the {} stream.", name);
+                }
+                if let Some(on_connect) = on_connect {
+                    on_connect();
+                }
+
+                // ? Only a connection that delivered something resets the backoff.
+                failures += 1;
+
+                let mut event = Event::new();
+                let mut buffer: Vec<u8> = vec![];
+
+                loop {
+                    match resp.chunk().await {
+                        Ok(None) => {
+                            warn!("The {} stream was closed by the server.", name);
+                            break;
                         }
-                    }
-                } else if level == "error" {
-                    match body.message {
-                        Some(message) => error!("{}", message),
-                        None => {
-                            if let Some(message) = body.msg {
-                                error!("{}", message)
+                        Ok(Some(chunk)) => {
+                            buffer.extend_from_slice(&chunk);
+
+                            // ? The chunks don't follow the lines, an incomplete line waits for the next chunk.
+                            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                                let raw: Vec<u8> = buffer.drain(..=end).collect();
+                                let line = String::from_utf8_lossy(&raw);
+
+                                match parse_event_line(&line, &mut event) {
+                                    ParseResult::Next => {}
+                                    ParseResult::Dispatch => {
+                                        if let Some(id) = &event.id {
+                                            last_event_id = Some(id.clone());
+                                        }
+                                        failures = 0;
+                                        if output::raw() {
+                                            trace!("📡 {}: {}", name, event.data.trim_end());
+                                        }
+                                        if !event.is_empty() {
+                                            on_event(mem::replace(&mut event, Event::new()));
+                                        }
+                                    }
+                                    ParseResult::SetRetry(retry) => base = retry,
+                                }
                             }
                         }
-                    }
-                } else if level == "debug" || level == "trace" {
-                    match body.message {
-                        Some(message) => categorize_debug_level(message),
-                        None => {
-                            if let Some(message) = body.msg {
-                                categorize_debug_level(message)
-                            }
+                        Err(e) => {
+                            warn!("The {} stream dropped: {}", name, e);
+                            break;
                         }
                     }
-                } else {
-                    trace!("{}", &event.data)
                 }
             }
+            Err(e) => {
+                failures += 1;
+                warn!("Could not connect to the {} stream: {}", name, e);
+            }
         }
-        Err(e) => {
-            error!("Error: {}", e);
+
+        dropped = true;
+
+        let delay = backoff(base, failures);
+        trace!("📡 Reconnecting to the {} stream in {:.1?}...", name, delay);
+        time::sleep(delay).await;
+    }
+}
+
+/// # Connect to a stream.
+/// Opens the event stream request, resuming from the last event ID if there's one.
+async fn connect(
+    url: &Url,
+    client: &Client,
+    last_event_id: Option<&str>,
+) -> reqwest::Result<Response> {
+    let mut request = client.get(url.clone()).header(ACCEPT, "text/event-stream");
+
+    if let Some(id) = last_event_id {
+        request = request.header("Last-Event-ID", id);
+    }
+
+    request.send().await?.error_for_status()
+}
+
+/// # Backoff delay.
+/// Exponential on the failures in a row, capped at `BACKOFF_MAX`, plus up to 50% of jitter.
+/// - The jitter keeps the three streams (and every other quix) from reconnecting all at once.
+fn backoff(base: Duration, failures: u32) -> Duration {
+    let delay = base
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(BACKOFF_MAX);
+
+    // ? A randomly seeded hasher is enough randomness for a jitter.
+    let max_jitter = delay.as_millis() as u64 / 2;
+    let jitter = RandomState::new().hash_one(SystemTime::now()) % (max_jitter + 1);
+
+    delay + Duration::from_millis(jitter)
+}
+
+/// # Stream the events and logs.
+/// Follows the build, status and logs streams together, for as long as the session lasts.
+/// - The streams are shared by every app in `ctxs`, the session (account and workspace) is the same for all of them.
+/// - The build stream asks the session for a new bundle through `signals`.
+/// - Only the app logs that go through `filter` are shown.
+/// - It only returns if a stream can't be followed at all, dropping it stops every stream.
+pub async fn stream(
+    ctxs: Vec<LinkContext>,
+    client: Client,
+    signals: UnboundedSender<Signal>,
+    filter: LogFilter,
+) -> Result<(), QuixError> {
+    let Some(ctx) = ctxs.first() else {
+        return Ok(());
+    };
+
+    let apps: Vec<String> = ctxs.iter().map(LinkContext::app_name).collect();
+
+    // ? The builders in the manifests, for the builds that don't tell which are running.
+    let builders: BTreeSet<String> = ctxs
+        .iter()
+        .flat_map(|ctx| ctx.builders.keys().cloned())
+        .collect();
+    let progress = Mutex::new(Progress::new(builders));
+    let building = Notify::new(); // Wakes the ticker up when a build starts.
+
+    tokio::try_join!(
+        build(ctx, &apps, &client, signals, &progress, &building),
+        status(ctx, &apps, &client),
+        logs(ctx, &client, &filter),
+        tick(&progress, &building),
+    )?;
+
+    Ok(())
+}
+
+fn match_event(event: Event, filter: &LogFilter) {
+    if event.data != "ping\n" {
+        let Ok(data) = serde_json::from_str::<LogEvent>(&event.data) else {
+            // ? Not every event has the expected shape, those are only traced.
+            trace!("{}", &event.data);
+            return;
+        };
+
+        let body = data.body;
+
+        let level = match data.level {
+            Some(level) => level,
+            None => match body.level {
+                Some(level) => level,
+                None => "info".to_string(),
+            },
+        };
+        let message = body.message.or(body.msg);
+        let app = data.subject.or(data.sender);
+
+        // ? The lines the filters leave out are dropped, in every output.
+        if !filter.matches(
+            app.as_deref(),
+            Level::parse(&level),
+            message.as_deref().unwrap_or_default(),
+        ) {
+            return;
+        }
+
+        // ? In JSON mode, the line is a record instead, if it's up to the threshold.
+        if output::json() {
+            if Level::parse(&level) > output::threshold() {
+                return;
+            }
+            if let Some(message) = message {
+                output::emit(Record::Log {
+                    level,
+                    message,
+                    app,
+                });
+            }
+            return;
+        }
+
+        // ? Tagged with its app, the logs of every app in the workspace come through the same stream.
+        let Some(message) = message.map(|message| match &app {
+            Some(app) => format!("[{}] {}", app_name(app), filter.highlight(&message)),
+            None => filter.highlight(&message),
+        }) else {
+            if !matches!(
+                level.as_str(),
+                "info" | "warning" | "error" | "debug" | "trace"
+            ) {
+                trace!("{}", &event.data)
+            }
+            return;
+        };
+
+        match level.as_str() {
+            "info" => info!("{}", message),
+            "warning" => warn!("{}", message),
+            "error" => error!("{}", message),
+            "debug" | "trace" => categorize_debug_level(message),
+            _ => trace!("{}", &event.data),
         }
     }
 }
@@ -200,3 +502,82 @@ fn categorize_debug_level(message: String) {
         debug!("{}", message);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_limit() {
+        let base = Duration::from_secs(1);
+        let within = |delay: Duration, secs: u64| {
+            delay >= Duration::from_secs(secs) && delay <= Duration::from_millis(secs * 1500)
+        };
+
+        assert!(within(backoff(base, 0), 1));
+        assert!(within(backoff(base, 1), 2));
+        assert!(within(backoff(base, 3), 8));
+        assert!(within(backoff(base, 5), 30));
+        assert!(within(backoff(base, u32::MAX), 30));
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_last_event_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/events", listener.local_addr().unwrap())).unwrap();
+
+        // ? Two connections, the first one sets a short retry and an event ID before dropping.
+        let server = thread::spawn(move || {
+            let mut resumed = vec![];
+            for body in ["retry: 10\nid: 7\ndata: first\n\n", "data: second\n\n"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_lowercase();
+                resumed.push(
+                    request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("last-event-id: "))
+                        .map(String::from),
+                );
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    body
+                )
+                .unwrap();
+            }
+            resumed
+        });
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = Client::new();
+        let events = async {
+            let first = rx.recv().await;
+            let second = rx.recv().await;
+            (first, second)
+        };
+
+        let received = tokio::select! {
+            _ = subscribe("test", url, &client, None, |event| {
+                let _ = tx.send(event.data);
+            }) => unreachable!("the stream never ends"),
+            received = time::timeout(Duration::from_secs(10), events) => received.unwrap(),
+        };
+
+        assert_eq!(
+            received,
+            (Some("first\n".to_string()), Some("second\n".to_string()))
+        );
+        assert_eq!(server.join().unwrap(), [None, Some("7".to_string())]);
+    }
+}
@@ -3,8 +3,11 @@
 //!
 //! ## Modules
 //! - Builder: Handles the Builder API endpoints.
+//! - Colossus: Handles the event and log streams.
+//! - Apps: Handles the Apps API endpoints.
 //!
 //! ## Builder
 //! - `link`: Link the app to the builder.
+pub mod apps;
 pub mod builder;
 pub mod colossus;
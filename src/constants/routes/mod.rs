@@ -6,11 +6,88 @@
 //! - `link`: The link to the VTEX IO Builder.
 //!   - `"link/{}.{}@{}?tsErrorsAsWarnings=false"`
 //!
-//! # Panics
-//! This module panics if the `link` route is not found.
+//! # Endpoints
+//! The base URLs default to the VTEX IO services, and can be overridden to point at a mock (e.g. `cargo run --example mock_hub`).
+//! - Builder: `--builder-url` or `QUIX_BUILDER_URL`.
+//! - Colossus: `--colossus-url` or `QUIX_COLOSSUS_URL`, also the base of the Apps API.
+//!
+//! # Context
+//! The routes are assembled from the `LinkContext`, so nothing is read from disk for each request.
+
+use std::sync::OnceLock;
+
+use crate::configs::LinkContext;
+
+/// # Builder URL
+/// Default base URL of the builder-hub.
+pub const BUILDER_URL: &str = "https://app.io.vtex.com";
 
-use crate::configs::Project;
-use crate::configs::Vtex;
+/// # Colossus URL
+/// Default base URL of the Colossus (Logging service).
+pub const COLOSSUS_URL: &str = "https://infra.io.vtex.com";
+
+/// # Endpoints struct
+/// The base URLs used for the session, set once when the arguments are parsed.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    pub builder: String,  // Builder-hub base URL
+    pub colossus: String, // Colossus base URL
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            builder: BUILDER_URL.to_string(),
+            colossus: COLOSSUS_URL.to_string(),
+        }
+    }
+}
+
+static ENDPOINTS: OnceLock<Endpoints> = OnceLock::new();
+
+/// # Configure the endpoints.
+/// Overrides the default base URLs, only the first call has any effect.
+pub fn configure(endpoints: Endpoints) {
+    let endpoints = Endpoints {
+        builder: endpoints.builder.trim_end_matches('/').to_string(),
+        colossus: endpoints.colossus.trim_end_matches('/').to_string(),
+    };
+
+    if ENDPOINTS.set(endpoints).is_err() {
+        warn!("The endpoints were already configured, ignoring the new ones.");
+    }
+}
+
+/// # Endpoints
+/// The base URLs in use, the defaults if they were never configured.
+pub fn endpoints() -> &'static Endpoints {
+    ENDPOINTS.get_or_init(Endpoints::default)
+}
+
+/// # Colossus route
+/// Assembles a Colossus route for the account and workspace in the link context, `path` being the part after it (e.g. `logs?level=debug`).
+pub fn colossus(ctx: &LinkContext, path: &str) -> String {
+    format!(
+        "{}/colossus/v0/{}/{}/{}",
+        endpoints().colossus,
+        ctx.account,
+        ctx.workspace,
+        path
+    )
+}
+
+/// # Apps route
+/// Assembles an Apps API route for the account and workspace, `path` being the part after it (e.g. `links`).
+/// - The Apps API lives on the same infra host as the Colossus.
+pub fn apps(account: &str, workspace: &str, path: &str) -> String {
+    format!(
+        "{}/apps/v0/{}/{}/{}",
+        endpoints().colossus,
+        account,
+        workspace,
+        path
+    )
+}
 
 /// # Routes Struct
 /// This struct contains the routes to the VTEX IO Builder.
@@ -25,41 +102,23 @@ pub enum Routes {
 /// This implementation contains the routes to the Builder.
 impl Routes {
     /// # Routes::assemble
-    /// This function assembles the routes.
-    pub fn assemble(route: Routes) -> String {
-        let project = Project::info().unwrap();
-        let env = Vtex::info();
-
+    /// This function assembles the routes, for the app in the link context.
+    /// - The builder is the one of the account and workspace in use, whatever the app vendor is.
+    pub fn assemble(route: Routes, ctx: &LinkContext) -> String {
         let base = format!(
-            "https://app.io.vtex.com/vtex.builder-hub/v0/{}/{}/_v/builder/0/",
-            project.vendor, env.workspace,
+            "{}/vtex.builder-hub/v0/{}/{}/_v/builder/0/",
+            endpoints().builder,
+            ctx.account,
+            ctx.workspace,
         );
 
-        let link_path = format!(
-            "link/{}.{}@{}?tsErrorsAsWarnings=false",
-            project.vendor, project.name, project.version
-        );
-
-        let relink_path = format!(
-            "relink/{}.{}@{}?tsErrorsAsWarnings=false",
-            project.vendor, project.name, project.version
-        );
-
-        let availability_path = format!(
-            "availability/{}.{}@{}",
-            project.vendor, project.name, project.version
-        );
-
-        let clean_path = format!(
-            "clean/{}.{}@{}",
-            project.vendor, project.name, project.version
-        );
+        let app = ctx.app();
 
         match route {
-            Routes::Link => format!("{}{}", base, link_path),
-            Routes::Relink => format!("{}{}", base, relink_path),
-            Routes::Availability => format!("{}{}", base, availability_path),
-            Routes::Clean => format!("{}{}", base, clean_path),
+            Routes::Link => format!("{}link/{}?tsErrorsAsWarnings=false", base, app),
+            Routes::Relink => format!("{}relink/{}?tsErrorsAsWarnings=false", base, app),
+            Routes::Availability => format!("{}availability/{}", base, app),
+            Routes::Clean => format!("{}clean/{}", base, app),
         }
     }
 }
@@ -0,0 +1,178 @@
+//! # Quix errors
+//! Every failure the CLI can run into, each one with a hint to fix it and its own process exit code.
+//! So scripts can tell "not logged in" apart from "build failed".
+//!
+//! # Exit codes
+//! | Code | Error |
+//! | :--: | :---- |
+//! | `3`  | Missing or invalid VTEX session. |
+//! | `4`  | Missing or invalid `manifest.json`. |
+//! | `5`  | The builder answered with an error. |
+//! | `6`  | The builder could not be reached. |
+//! | `7`  | A Colossus stream failed. |
+//! | `8`  | IO error. |
+//! | `9`  | Error while zipping the project. |
+//! | `10` | Invalid quix settings (e.g. a log filter). |
+//! | `11` | The build failed (`link --once`). |
+//! | `12` | The build didn't end in time (`link --once --timeout`). |
+//!
+//! # Examples
+//! ```rust
+//! if let Err(e) = commands::link(args) {
+//!     errors::report(&e);
+//!     process::exit(e.exit_code());
+//! }
+//! ```
+
+use std::{fmt, io};
+
+use zip::result::ZipError;
+
+use crate::output::{self, Record};
+
+/// # QuixError enum.
+/// The errors surfaced to the user.
+#[derive(Debug)]
+pub enum QuixError {
+    Session(String),  // Not logged in, or the session can't be read
+    Manifest(String), // The manifest.json is missing or invalid
+    Builder {
+        status: u16,     // HTTP status
+        code: String,    // `VTEXError` code
+        message: String, // `VTEXError` message
+    },
+    Network(reqwest::Error), // The builder couldn't be reached
+    Colossus(String),        // An event stream failed
+    Io(io::Error),
+    Zip(ZipError),
+    Config(String),  // The quix settings are invalid
+    Build(String),   // The builder reported a failed build
+    Timeout(String), // The build didn't end in time
+}
+
+/// # Implements the `QuixError` and define the **help**, **code**, **exit_code** and **is_transient** methods.
+impl QuixError {
+    /// # Help hint.
+    /// What the user can do about the error.
+    pub fn help(&self) -> &'static str {
+        match self {
+            QuixError::Session(_) => "Login to your account using the VTEX CLI, then try again.",
+            QuixError::Manifest(_) => "Are you sure you are in a project directory? Check the manifest.json, or use --quicker to skip the checks.",
+            QuixError::Builder { code, .. } if code == "link_on_production" => "Action not allowed on production, change to a development environment to link your project.",
+            QuixError::Builder { status, .. } if *status >= 500 => "This looks like a Server Error (500ish). Please try again later.",
+            QuixError::Builder { .. } => "Check your VTEX credentials and the account and workspace in use, try logging in again.",
+            QuixError::Network(_) => "Check your internet connection, then try again.",
+            QuixError::Colossus(_) => "The builder events could not be followed, try linking again.",
+            QuixError::Io(_) => "Check if the project files exist and can be read.",
+            QuixError::Zip(_) => "Some project file could not be bundled, check the ignore rules and try again.",
+            QuixError::Config(_) => "Check the quix settings, on the command line or in the project quix.json.",
+            QuixError::Build(_) => "The project does not build on VTEX IO, fix the errors above and try again.",
+            QuixError::Timeout(_) => "The builder may be busy, try again or give it a longer --timeout.",
+        }
+    }
+
+    /// # Code.
+    /// A stable name for the kind of error, for the JSON output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            QuixError::Session(_) => "session",
+            QuixError::Manifest(_) => "manifest",
+            QuixError::Builder { .. } => "builder",
+            QuixError::Network(_) => "network",
+            QuixError::Colossus(_) => "colossus",
+            QuixError::Io(_) => "io",
+            QuixError::Zip(_) => "zip",
+            QuixError::Config(_) => "config",
+            QuixError::Build(_) => "build",
+            QuixError::Timeout(_) => "timeout",
+        }
+    }
+
+    /// # Exit code.
+    /// A distinct process exit code for each kind of error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            QuixError::Session(_) => 3,
+            QuixError::Manifest(_) => 4,
+            QuixError::Builder { .. } => 5,
+            QuixError::Network(_) => 6,
+            QuixError::Colossus(_) => 7,
+            QuixError::Io(_) => 8,
+            QuixError::Zip(_) => 9,
+            QuixError::Config(_) => 10,
+            QuixError::Build(_) => 11,
+            QuixError::Timeout(_) => 12,
+        }
+    }
+
+    /// # Is transient.
+    /// Tells if trying again later may work, like server errors and network blips.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            QuixError::Builder { status, .. } => *status >= 500,
+            QuixError::Network(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for QuixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuixError::Session(message) => write!(f, "{}", message),
+            QuixError::Manifest(message) => write!(f, "{}", message),
+            QuixError::Builder {
+                status,
+                code,
+                message,
+            } => write!(f, "{:?}: {} ({})", code, message, status),
+            QuixError::Network(e) => write!(f, "Could not reach the builder: {}", e),
+            QuixError::Colossus(message) => write!(f, "{}", message),
+            QuixError::Io(e) => write!(f, "IO error: {}", e),
+            QuixError::Zip(e) => write!(f, "Error while zipping the project: {}", e),
+            QuixError::Config(message) => write!(f, "{}", message),
+            QuixError::Build(message) => write!(f, "{}", message),
+            QuixError::Timeout(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for QuixError {}
+
+impl From<io::Error> for QuixError {
+    fn from(e: io::Error) -> Self {
+        QuixError::Io(e)
+    }
+}
+
+impl From<ZipError> for QuixError {
+    fn from(e: ZipError) -> Self {
+        QuixError::Zip(e)
+    }
+}
+
+impl From<reqwest::Error> for QuixError {
+    fn from(e: reqwest::Error) -> Self {
+        QuixError::Network(e)
+    }
+}
+
+impl From<notify::Error> for QuixError {
+    fn from(e: notify::Error) -> Self {
+        QuixError::Io(io::Error::other(e))
+    }
+}
+
+/// # Report an error.
+/// Logs the error along with its help hint, or emits it as a record in JSON mode.
+pub fn report(e: &QuixError) {
+    help!("{}", e.help());
+    error!("{}", e);
+
+    output::emit(Record::Error {
+        code: e.code().to_string(),
+        exit_code: e.exit_code(),
+        message: e.to_string(),
+        help: e.help().to_string(),
+    });
+}
@@ -1,10 +1,9 @@
-#![allow(clippy::unit_arg)]
-
 //! # Initiates the CLI
 //! Thats the entry point for the CLI, and is the first function to be executed.
 //!
 //! # Subcommands
-//! - `link`: Link the app to the builder.
+//! - `link`: Link the app to the builder, until stopped with Ctrl-C.
+//! - `unlink`: Unlink an app, or every app, from the workspace.
 //!
 //! # Examples
 //! Base:
@@ -18,10 +17,15 @@
 //! # OR
 //! quix link -c
 //! ```
-//! # Panics
-//! This function will panic if the **VTEX CLI** is not properly installed and if your login and authentication token are not set.
+//! # Exit codes
+//! Each kind of error exits with its own code, e.g. `3` if the **VTEX CLI** is not properly installed or your login and authentication token are not set.
 //!
-//! This is because the CLI will not be able to authenticate with the **VTEX API**.
+//! The full list is in the `errors` module.
+
+// * Output
+// First of all, as its logging macros are used by the modules below.
+#[macro_use]
+mod output; // Output, text logs or JSON lines.
 
 // * General modules
 // We import them here to expose for the rest of the codebase.
@@ -31,30 +35,89 @@ mod commands; // Commands, like the link command.
 mod configs; // Configs, like the VTEX account and the session token.
 mod connections; // Connections, like the VTEX Builder API.
 mod constants; // Constants, like the routes.
+mod errors; // Errors, like the missing session.
 mod utils; // Utility functions, like the JSON parser.
 
 // * Misc
 use cli::args; // CLI arguments.
-use human_panic::setup_panic; // Human panic, for a better error handling.
-
-// * Macros
-#[macro_use]
-extern crate logi;
+use configs::configstore; // VTEX configstore location.
+use constants::routes::{self, Endpoints}; // API endpoints.
+use human_panic::{setup_panic, Metadata}; // Human panic, for a better error handling.
+use output::Level; // Log level.
+use std::io::{self, IsTerminal}; // Terminal detection, for the greetings.
+use std::path::PathBuf; // Configstore location.
+use std::process; // Exit codes.
 
 /// # Main function.
 /// Here we start the CLI, and parse the arguments.
-fn main() {
+/// - The whole link session runs on a single threaded runtime, the blocking work (zipping, hashing) goes to its blocking pool.
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
     // * Setting up the panic handler
-    setup_panic!(Metadata {
-        name: env!("CARGO_PKG_NAME").into(),
-        version: env!("CARGO_PKG_VERSION").into(),
-        authors: env!("CARGO_PKG_AUTHORS").into(),
-        homepage: "https://github.com/rafaelrcamargo/quix".into(),
-    }); // !!! Setup the panic handler.
+    setup_panic!(
+        Metadata::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+            .authors(env!("CARGO_PKG_AUTHORS"))
+            .homepage("https://github.com/rafaelrcamargo/quix")
+    ); // !!! Setup the panic handler.
 
     // * Main task (Arguments, Parsing, ...)
     let matches = args::matches();
 
+    // ? The output goes first, as it decides if anything below is logged.
+    let verbose = matches
+        .get_one::<u8>("verbose")
+        .copied()
+        .unwrap_or_default();
+    let quiet = matches.get_one::<bool>("quiet").is_some_and(|x| *x);
+    // ? The most explicit wins: `--quiet`, then `-v`, then `--log-level`, then `QUIX_LOG_LEVEL`.
+    let level = match (quiet, verbose, matches.get_one::<Level>("log-level")) {
+        (true, _, _) => Level::Error,
+        (_, 1, _) => Level::Debug,
+        (_, 2.., _) => Level::Trace,
+        (_, _, Some(level)) => *level,
+        (_, _, None) => Level::Info,
+    };
+
+    output::configure(output::Config {
+        format: matches
+            .get_one::<output::Format>("output")
+            .copied()
+            .unwrap_or_default(),
+        level,
+        raw: verbose >= 3,
+    });
+
+    // ? Point the clients to the configured endpoints, before any request is made.
+    let url = |id: &str| matches.get_one::<String>(id).cloned().unwrap_or_default();
+    routes::configure(Endpoints {
+        builder: url("builder-url"),
+        colossus: url("colossus-url"),
+    });
+
+    // ? Where the VTEX session is, before it's read.
+    configstore::configure(matches.get_one::<PathBuf>("config").cloned());
+
+    // ? The greetings are for humans, not for quiet runs or pipes.
+    if !quiet && io::stdout().is_terminal() {
+        greet();
+    }
+
+    let result = match matches.subcommand() {
+        Some(("link", args)) => commands::link(args).await,
+        Some(("unlink", args)) => commands::unlink(args).await,
+        _ => unreachable!("Invalid entry."), // !!! Shouldn't happen, but just in case, who knows?
+    };
+
+    // ? Each kind of error has its own exit code, so scripts can tell them apart.
+    if let Err(e) = result {
+        errors::report(&e);
+        process::exit(e.exit_code());
+    }
+}
+
+/// # Greetings
+/// Some dialogs before anything else, for the humans at the terminal.
+fn greet() {
     // * Dialogs (Greetings, warnings, ...)
     let dialogs = [
         (
@@ -78,9 +141,4 @@ fn main() {
 
     warn!("This is a beta version of the CLI, and may not be stable. 😬\n");
     trace!("We're about to go fast, fasten your seat belts. 🚀\n");
-
-    match matches.subcommand() {
-        Some(("link", args)) => commands::link(args),
-        _ => unreachable!("Invalid entry."), // !!! Shouldn't happen, but just in case, who knows?
-    }
 }
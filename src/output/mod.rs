@@ -0,0 +1,374 @@
+//! # Output
+//! How the CLI talks back, either `logi` text lines for humans, or JSON lines for the tools (`--output json`).
+//! - In JSON mode, the text logs are silenced, and every record goes to stdout as one JSON object per line.
+//! - The logging macros used all over the codebase (`info!`, `warn!`, ...) are defined here, wrapping the `logi` ones.
+//!
+//! # Levels
+//! The text logs below the threshold level are dropped, `info` by default.
+//! - `-v` for `debug`, `-vv` for `trace`, and `-vvv` to also trace the raw stream events.
+//! - `--log-level <LEVEL>` (or `QUIX_LOG_LEVEL`) to set it by name, and `--quiet` for the errors only.
+//! - When several are set, `--quiet` wins over `-v`, and `-v` over `--log-level`.
+//!
+//! # Live lines
+//! The lines redrawn in place on a terminal (the build progress) are cleared by every text log before it's printed.
+//! - The next redraw puts them back, below the log line.
+//!
+//! # Examples
+//! ```rust
+//! output::emit(Record::FileChanged { path, change: "write".to_string() });
+//! ```
+//!
+//! # Schema
+//! Every line has the `ts` (milliseconds since the epoch) and `event` fields, plus the fields of its event:
+//! | Event | Fields |
+//! | :---- | :----- |
+//! | `file_changed` | `path`, `change` (`write` or `remove`) |
+//! | `upload_started` | `kind` (`link` or `relink`), `app` |
+//! | `upload_finished` | `kind`, `app`, `bytes`, `ms` |
+//! | `build` | `status`, `app`, `builders`, `timings`, `message`, `errors` |
+//! | `receive` | `status`, `app`, `message` |
+//! | `log` | `level`, `message`, `app` |
+//! | `error` | `code`, `exit_code`, `message`, `help` |
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    str::FromStr,
+    sync::{Mutex, OnceLock, PoisonError},
+};
+
+use serde::Serialize;
+
+use crate::configs::vtex::now_millis;
+
+/// # Output format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Text, // `logi` lines
+    Json, // JSON lines
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format `{}`, use `text` or `json`", s)),
+        }
+    }
+}
+
+/// # Log level.
+/// Ordered from the most to the least important, a threshold lets everything up to it through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    #[default]
+    Info, // Also the success messages
+    Debug,
+    Trace,
+}
+
+/// # Implements the `Level` and define the **parse** and **colossus** methods.
+impl Level {
+    /// # Parse a level.
+    /// The level names used by the Colossus, `info` for the unknown ones.
+    pub fn parse(level: &str) -> Level {
+        level.parse().unwrap_or_default()
+    }
+
+    /// # Colossus level.
+    /// The `level=` sent to the Colossus, so the lines below the threshold aren't even streamed.
+    /// - The Colossus has no `trace`, the `debug` lines are split locally.
+    pub fn colossus(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug | Level::Trace => "debug",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!(
+                "unknown level `{}`, use `error`, `warn`, `info`, `debug` or `trace`",
+                s
+            )),
+        }
+    }
+}
+
+/// # Output config.
+/// How the CLI talks back, set once when the arguments are parsed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Config {
+    pub format: Format,
+    pub level: Level, // Threshold of the text logs, and of the Colossus lines
+    pub raw: bool,    // Trace the raw stream events too
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// # Configure the output.
+/// Sets the output config, only the first call has any effect.
+pub fn configure(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+fn config() -> Config {
+    CONFIG.get().copied().unwrap_or_default()
+}
+
+/// # Text
+/// Tells if the text logs are on.
+pub fn text() -> bool {
+    config().format == Format::Text
+}
+
+/// # Threshold
+/// The least important level let through.
+pub fn threshold() -> Level {
+    config().level
+}
+
+/// # Enabled
+/// Tells if a text log of this level is printed.
+pub fn enabled(level: Level) -> bool {
+    text() && level <= threshold()
+}
+
+/// # Raw
+/// Tells if the raw stream events are traced.
+pub fn raw() -> bool {
+    config().raw && enabled(Level::Trace)
+}
+
+/// # JSON
+/// Tells if the records go to stdout as JSON lines.
+pub fn json() -> bool {
+    !text()
+}
+
+/// # Record enum.
+/// Everything worth telling a tool about, tagged by its `event`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Record {
+    FileChanged {
+        path: String,
+        change: String,
+    },
+    UploadStarted {
+        kind: String,
+        app: String,
+    },
+    UploadFinished {
+        kind: String,
+        app: String,
+        bytes: u64,
+        ms: u64,
+    },
+    Build {
+        status: String,
+        app: Option<String>, // `vendor.name`, when the event tells
+        builders: Vec<String>,
+        timings: BTreeMap<String, u64>, // Builder name => milliseconds
+        message: Option<String>,
+        errors: Vec<String>,
+    },
+    Receive {
+        status: String,
+        app: Option<String>,
+        message: Option<String>,
+    },
+    Log {
+        level: String,
+        message: String,
+        app: Option<String>,
+    },
+    Error {
+        code: String,
+        exit_code: i32,
+        message: String,
+        help: String,
+    },
+}
+
+/// # Line struct.
+/// A record, stamped with its time.
+#[derive(Serialize)]
+struct Line<'a> {
+    ts: u64,
+    #[serde(flatten)]
+    record: &'a Record,
+}
+
+/// # Emit a record.
+/// Prints the record as a JSON line, only in JSON mode.
+pub fn emit(record: Record) {
+    if text() {
+        return;
+    }
+
+    let line = Line {
+        ts: now_millis(),
+        record: &record,
+    };
+
+    if let Ok(line) = serde_json::to_string(&line) {
+        println!("{}", line);
+    }
+}
+
+/// How many live lines are on screen, the cursor is at the end of the last one.
+static LIVE: Mutex<usize> = Mutex::new(0);
+
+/// # Draw the live lines.
+/// Replaces the live lines on screen with `lines`.
+pub fn draw_live(lines: &[String]) {
+    let mut drawn = LIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut stdout = io::stdout().lock();
+
+    let _ = write!(stdout, "{}{}", erase(*drawn), lines.join("\n"));
+    let _ = stdout.flush();
+    *drawn = lines.len();
+}
+
+/// # Clear the live lines.
+/// Removes the live lines, so a log line can take their place.
+pub fn clear_live() {
+    let mut drawn = LIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    if *drawn == 0 {
+        return;
+    }
+
+    let mut stdout = io::stdout().lock();
+    let _ = write!(stdout, "{}", erase(*drawn));
+    let _ = stdout.flush();
+    *drawn = 0;
+}
+
+/// # Erase
+/// The escapes clearing `lines` lines, from the last one up to the first one.
+fn erase(lines: usize) -> String {
+    match lines {
+        0 => String::new(),
+        n => format!("\r\x1b[2K{}", "\x1b[1A\x1b[2K".repeat(n - 1)),
+    }
+}
+
+// * * * Logging macros, the `logi` ones, silenced below the threshold and in JSON mode. * * *
+// * They clear the live lines first, so a log line never lands on top of them.
+
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Trace) {
+            $crate::output::clear_live();
+            logi::trace!($($arg)+)
+        }
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Debug) {
+            $crate::output::clear_live();
+            logi::debug!($($arg)+)
+        }
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Info) {
+            $crate::output::clear_live();
+            logi::info!($($arg)+)
+        }
+    };
+}
+
+macro_rules! success {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Info) {
+            $crate::output::clear_live();
+            logi::success!($($arg)+)
+        }
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Warn) {
+            $crate::output::clear_live();
+            logi::warn!($($arg)+)
+        }
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Error) {
+            $crate::output::clear_live();
+            logi::error!($($arg)+)
+        }
+    };
+}
+
+macro_rules! help {
+    ($($arg:tt)+) => {
+        if $crate::output::enabled($crate::output::Level::Error) {
+            $crate::output::clear_live();
+            logi::help!($($arg)+)
+        }
+    };
+}
+
+macro_rules! custom {
+    ($level: expr, $message: expr) => {
+        if $crate::output::enabled($crate::output::Level::Info) {
+            $crate::output::clear_live();
+            logi::custom!($level, $message)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn erases_from_the_last_line_up() {
+        assert_eq!(erase(0), "");
+        assert_eq!(erase(1), "\r\x1b[2K");
+        assert_eq!(erase(3), "\r\x1b[2K\x1b[1A\x1b[2K\x1b[1A\x1b[2K");
+    }
+
+    #[test]
+    fn logs_clear_the_live_lines() {
+        // ? The other tests log too, so only the cleared state can be asserted.
+        draw_live(&[
+            "⠙ Building react 1.2s".to_string(),
+            "⠙ Building store 0.4s".to_string(),
+        ]);
+        info!("A log line takes their place.");
+        assert_eq!(*LIVE.lock().unwrap(), 0);
+
+        draw_live(&["⠹ Building react 1.3s".to_string()]);
+        clear_live();
+        assert_eq!(*LIVE.lock().unwrap(), 0);
+    }
+}
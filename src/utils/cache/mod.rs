@@ -0,0 +1,227 @@
+//! # Change cache module.
+//! Keeps the hash of the last content sent to the builder for each file.
+//! Editors often save without changing anything, so this lets us skip the redundant uploads.
+//!
+//! # Examples
+//! ```rust
+//! let mut cache = ChangeCache::default();
+//! cache.insert(path.clone(), &bytes); // Seeded by the bundle.
+//!
+//! let mut pending = Pending::default();
+//! cache.stage(&path, &bytes, &mut pending); // false, nothing changed.
+//! cache.commit(pending); // Once the builder has the changes.
+//! ```
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// # Shared cache.
+/// The cache is shared between the watcher and the event streams, as both can send files.
+pub type SharedCache = Arc<Mutex<ChangeCache>>;
+
+/// # Pending struct.
+/// The changes of a relink, kept out of the cache until the builder has them.
+/// - A relink that fails leaves the cache as it was, so the same content is sent again.
+#[derive(Debug, Default)]
+pub struct Pending {
+    hashes: Vec<(PathBuf, Option<u64>)>, // Path => new content hash, `None` once removed
+}
+
+/// # ChangeCache struct.
+/// Maps each file path to the hash of its last sent content.
+#[derive(Debug, Default)]
+pub struct ChangeCache {
+    hashes: HashMap<PathBuf, u64>, // Path => content hash
+    skipped: usize,                // Uploads skipped during the session
+}
+
+/// # Implements the `ChangeCache` methods.
+impl ChangeCache {
+    /// # Hash the content.
+    /// Not a cryptographic hash, only used to tell if the bytes changed.
+    fn hash(content: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// # Insert a file.
+    /// Records the content as sent, used while seeding from the bundle.
+    pub fn insert(&mut self, path: PathBuf, content: &[u8]) {
+        self.hashes.insert(path, Self::hash(content));
+    }
+
+    /// # Stage a file.
+    /// Returns `true` if the content changed since it was last sent, staging the new hash in `pending`.
+    /// - Otherwise, the skipped counter goes up.
+    pub fn stage(&mut self, path: &Path, content: &[u8], pending: &mut Pending) -> bool {
+        let hash = Self::hash(content);
+
+        if self.hashes.get(path) == Some(&hash) {
+            self.skipped += 1;
+            return false;
+        }
+
+        pending.hashes.push((path.to_path_buf(), Some(hash)));
+        true
+    }
+
+    /// # Stage a removal.
+    /// Stages the removal of `path`, or of every file under it if it was a directory, returning the paths removed in order.
+    pub fn stage_removal(&self, path: &Path, pending: &mut Pending) -> Vec<PathBuf> {
+        let mut removed: Vec<PathBuf> = self
+            .hashes
+            .keys()
+            .filter(|p| *p != path && p.starts_with(path))
+            .cloned()
+            .collect();
+        removed.sort();
+
+        if removed.is_empty() {
+            removed.push(path.to_path_buf());
+        }
+
+        pending
+            .hashes
+            .extend(removed.iter().map(|path| (path.clone(), None)));
+        removed
+    }
+
+    /// # Commit the changes.
+    /// Records the `pending` changes, once the builder has them.
+    pub fn commit(&mut self, pending: Pending) {
+        for (path, hash) in pending.hashes {
+            match hash {
+                Some(hash) => self.hashes.insert(path, hash),
+                None => self.hashes.remove(&path),
+            };
+        }
+    }
+
+    /// # Clear the cache.
+    /// Forgets every file, used before a new bundle seeds it again.
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+    }
+
+    /// # Digest.
+    /// A single hash for every file in the cache, it only changes when a path or a content does.
+    pub fn digest(&self) -> u64 {
+        let mut entries: Vec<(&PathBuf, &u64)> = self.hashes.iter().collect();
+        entries.sort(); // The map has no order, the digest must not depend on it.
+
+        let mut hasher = DefaultHasher::new();
+        entries.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// # Skipped uploads.
+    /// How many uploads were skipped during the session.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # Send
+    /// Stages and commits a file, as a relink that went through does.
+    fn send(cache: &mut ChangeCache, path: &Path, content: &[u8]) -> bool {
+        let mut pending = Pending::default();
+        let changed = cache.stage(path, content, &mut pending);
+        cache.commit(pending);
+        changed
+    }
+
+    #[test]
+    fn skips_the_unchanged_content() {
+        let mut cache = ChangeCache::default();
+        let path = Path::new("/project/react/index.tsx");
+        cache.insert(path.to_path_buf(), b"export {}");
+
+        assert!(!send(&mut cache, path, b"export {}"));
+        assert!(!send(&mut cache, path, b"export {}"));
+        assert_eq!(cache.skipped(), 2);
+
+        assert!(send(&mut cache, path, b"export default 42"));
+        assert!(!send(&mut cache, path, b"export default 42"));
+        assert_eq!(cache.skipped(), 3);
+    }
+
+    #[test]
+    fn keeps_the_changes_out_until_committed() {
+        let mut cache = ChangeCache::default();
+        let path = Path::new("/project/react/index.tsx");
+        cache.insert(path.to_path_buf(), b"export {}");
+
+        // ? The relink failed, the pending changes are dropped.
+        let mut pending = Pending::default();
+        assert!(cache.stage(path, b"export default 42", &mut pending));
+        assert_eq!(cache.stage_removal(path, &mut pending), [path]);
+        drop(pending);
+
+        assert!(send(&mut cache, path, b"export default 42"));
+        assert!(!send(&mut cache, path, b"export default 42"));
+    }
+
+    #[test]
+    fn sends_a_file_again_once_removed() {
+        let mut cache = ChangeCache::default();
+        let path = Path::new("/project/react/index.tsx");
+        cache.insert(path.to_path_buf(), b"export {}");
+
+        let mut pending = Pending::default();
+        cache.stage_removal(path, &mut pending);
+        cache.commit(pending);
+        assert!(send(&mut cache, path, b"export {}"));
+
+        cache.clear();
+        assert!(send(&mut cache, path, b"export {}"));
+    }
+
+    #[test]
+    fn removes_the_files_under_a_directory() {
+        let mut cache = ChangeCache::default();
+        for path in ["/p/react/a/b.tsx", "/p/react/a/c/d.tsx", "/p/react/ab.tsx"] {
+            cache.insert(PathBuf::from(path), b"");
+        }
+
+        let mut pending = Pending::default();
+        assert_eq!(
+            cache.stage_removal(Path::new("/p/react/a"), &mut pending),
+            [
+                Path::new("/p/react/a/b.tsx"),
+                Path::new("/p/react/a/c/d.tsx")
+            ]
+        );
+        cache.commit(pending);
+
+        // ? Nothing left under it, the path itself is the one removed.
+        let mut pending = Pending::default();
+        assert_eq!(
+            cache.stage_removal(Path::new("/p/react/a"), &mut pending),
+            [Path::new("/p/react/a")]
+        );
+        assert!(!send(&mut cache, Path::new("/p/react/ab.tsx"), b""));
+    }
+
+    #[test]
+    fn digests_the_paths_and_contents() {
+        let mut a = ChangeCache::default();
+        let mut b = ChangeCache::default();
+        a.insert(PathBuf::from("/p/a"), b"1");
+        a.insert(PathBuf::from("/p/b"), b"2");
+        b.insert(PathBuf::from("/p/b"), b"2");
+        b.insert(PathBuf::from("/p/a"), b"1");
+        assert_eq!(a.digest(), b.digest());
+
+        send(&mut b, Path::new("/p/a"), b"3");
+        assert_ne!(a.digest(), b.digest());
+    }
+}
@@ -0,0 +1,256 @@
+//! # Project filter module.
+//! Here we decide which files of the project are sent to the builder, and which changes are watched.
+//! The rules are read from the `.vtexignore` file (falling back to the `.gitignore`), following the gitignore semantics.
+//! When the builders are known, only their directories and the root files are kept.
+//!
+//! # Examples
+//! ```rust
+//! let filter = Filter::new(&path).with_builders(project.builders.keys());
+//! filter.is_ignored(&path.join("node_modules/react/index.js")); // true
+//! filter.is_ignored(&path.join("docs/README.md")); // true, unless there's a `docs` builder
+//! ```
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+};
+
+/// # Ignore files.
+/// The files that can hold the ignore rules, in order of precedence, only the first one found is used.
+const IGNORE_FILES: [&str; 2] = [".vtexignore", ".gitignore"];
+
+/// # Default rules.
+/// These are always applied, the ignore file can still override them with a negation (`!`).
+const DEFAULT_RULES: [&str; 2] = [".git/", "node_modules/"];
+
+/// # Root files.
+/// Files outside the builders directories that the builder still needs.
+const ROOT_FILES: [&str; 2] = ["manifest.json", "package.json"];
+
+/// # Filter struct.
+/// Holds the compiled ignore rules for a project directory.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    root: PathBuf,                     // Project root, where the rules are anchored
+    rules: Gitignore,                  // Compiled rules
+    builders: Option<HashSet<String>>, // Builders directories, when known
+}
+
+/// # Implements the `Filter` and define the **new**, **with_builders** and **is_ignored** methods.
+///
+/// # Examples
+/// ```rust
+/// let filter = Filter::new(&path);
+/// ```
+impl Filter {
+    pub fn new(root: &Path) -> Filter {
+        let mut builder = GitignoreBuilder::new(root);
+
+        // ? Add the default rules first, so the ignore file takes precedence over them.
+        for rule in DEFAULT_RULES {
+            if let Err(e) = builder.add_line(None, rule) {
+                error!("Invalid default ignore rule {}: {}", rule, e);
+            }
+        }
+
+        // ? Add the rules from the first ignore file found.
+        if let Some(file) = IGNORE_FILES
+            .iter()
+            .map(|f| root.join(f))
+            .find(|f| f.is_file())
+        {
+            trace!("🙈 Using the ignore rules from {:?}", file);
+
+            if let Some(e) = builder.add(&file) {
+                warn!("Some rules in {:?} could not be parsed: {}", file, e);
+            }
+        }
+
+        let rules = match builder.build() {
+            Ok(rules) => rules,
+            Err(e) => {
+                error!("Error compiling the ignore rules: {}", e);
+                Gitignore::empty()
+            }
+        };
+
+        Filter {
+            root: root.to_path_buf(),
+            rules,
+            builders: None,
+        }
+    }
+
+    /// # Scope the filter to the builders.
+    /// Only the builders directories (e.g. `react/`, `store/`) and the root files are kept.
+    /// - Without any builder, nothing is scoped, as we can't tell what the builder needs.
+    pub fn with_builders<'a>(mut self, builders: impl IntoIterator<Item = &'a String>) -> Filter {
+        let builders: HashSet<String> = builders.into_iter().cloned().collect();
+
+        if !builders.is_empty() {
+            self.builders = Some(builders);
+        }
+
+        self
+    }
+
+    /// # Check if a path is ignored.
+    /// Paths outside the project root are always ignored, the root itself never is.
+    /// - A path inside an ignored directory is also ignored.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return true,
+        };
+
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        // ? Outside the builders directories, only the root files are kept.
+        if let Some(builders) = &self.builders {
+            let in_builder = match relative.components().next() {
+                Some(Component::Normal(first)) => {
+                    first.to_str().is_some_and(|first| builders.contains(first))
+                }
+                _ => false,
+            };
+            let is_root_file = relative
+                .to_str()
+                .is_some_and(|relative| ROOT_FILES.contains(&relative));
+
+            if !in_builder && !is_root_file {
+                return true;
+            }
+        }
+
+        self.rules
+            .matched_path_or_any_parents(relative, path.is_dir())
+            .is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    /// # Project
+    /// A throwaway project with the given files, an empty content makes a directory, removed on drop.
+    struct Project(PathBuf);
+
+    impl std::ops::Deref for Project {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn project(name: &str, files: &[(&str, &str)]) -> Project {
+        let root = env::temp_dir().join(format!("quix-filter-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        for (path, content) in files {
+            let path = root.join(path);
+            if content.is_empty() {
+                fs::create_dir_all(&path).unwrap();
+            } else {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+            }
+        }
+
+        Project(root)
+    }
+
+    #[test]
+    fn ignores_the_default_rules() {
+        let root = project("defaults", &[]);
+        let filter = Filter::new(&root);
+
+        assert!(filter.is_ignored(&root.join("node_modules/react/index.js")));
+        assert!(filter.is_ignored(&root.join(".git/HEAD")));
+        assert!(!filter.is_ignored(&root.join("react/index.tsx")));
+        assert!(!filter.is_ignored(&root));
+        assert!(filter.is_ignored(Path::new("/elsewhere/index.tsx")));
+    }
+
+    #[test]
+    fn negates_a_rule() {
+        let root = project(
+            "negation",
+            &[(".vtexignore", "*.log\n!keep.log\n!node_modules/\n")],
+        );
+        let filter = Filter::new(&root);
+
+        assert!(filter.is_ignored(&root.join("react/debug.log")));
+        assert!(!filter.is_ignored(&root.join("react/keep.log")));
+        // ? The ignore file can override the default rules.
+        assert!(!filter.is_ignored(&root.join("node_modules/react/index.js")));
+    }
+
+    #[test]
+    fn anchors_a_rule_to_the_root() {
+        let root = project("anchored", &[(".vtexignore", "/build\n")]);
+        let filter = Filter::new(&root);
+
+        assert!(filter.is_ignored(&root.join("build")));
+        assert!(filter.is_ignored(&root.join("build/index.js")));
+        assert!(!filter.is_ignored(&root.join("react/build")));
+    }
+
+    #[test]
+    fn matches_only_directories() {
+        let root = project(
+            "directories",
+            &[
+                (".vtexignore", "dist/\n"),
+                ("react/dist", ""),
+                ("store/dist", "a file"),
+            ],
+        );
+        let filter = Filter::new(&root);
+
+        assert!(filter.is_ignored(&root.join("react/dist")));
+        assert!(filter.is_ignored(&root.join("react/dist/index.js")));
+        assert!(!filter.is_ignored(&root.join("store/dist")));
+    }
+
+    #[test]
+    fn falls_back_to_the_gitignore() {
+        let root = project("fallback", &[(".gitignore", "*.map\n")]);
+        assert!(Filter::new(&root).is_ignored(&root.join("react/index.js.map")));
+
+        // ? Only the first ignore file found is used.
+        fs::write(root.join(".vtexignore"), "*.log\n").unwrap();
+        let filter = Filter::new(&root);
+        assert!(filter.is_ignored(&root.join("react/debug.log")));
+        assert!(!filter.is_ignored(&root.join("react/index.js.map")));
+    }
+
+    #[test]
+    fn scopes_to_the_builders() {
+        let root = project("builders", &[]);
+        let builders = ["react".to_string()];
+        let filter = Filter::new(&root).with_builders(builders.iter());
+
+        assert!(!filter.is_ignored(&root.join("react/index.tsx")));
+        assert!(!filter.is_ignored(&root.join("manifest.json")));
+        assert!(filter.is_ignored(&root.join("docs/README.md")));
+
+        // ? Without builders, nothing is scoped.
+        let filter = Filter::new(&root).with_builders([].iter());
+        assert!(!filter.is_ignored(&root.join("docs/README.md")));
+    }
+}
@@ -1,64 +1,348 @@
 //! # Send dir module.
 //! Here we handle the process for zipping the project directory, thats useful in the first link command.
+//! The zip is streamed, so the upload can start while the rest of the project is still being zipped.
 //!
 //! _That can also be useful for later implementing the `deploy` workflow._
 
-use std::fs::File;
-use std::io::{prelude::*, Cursor, Write};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufWriter, Write};
 use std::iter::Iterator;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use bytes::Bytes;
+use futures_util::{stream, Stream};
+use tokio::{
+    sync::mpsc,
+    task::{self, JoinHandle},
+};
 use walkdir::{DirEntry, WalkDir};
-use zip::{result::ZipError, write::FileOptions, ZipWriter};
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use super::{cache::SharedCache, filter::Filter};
+
+/// # Already compressed extensions.
+/// Deflating these again costs time and saves (almost) nothing, so the `auto` policy stores them.
+const COMPRESSED_EXTENSIONS: [&str; 16] = [
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "ico", "woff", "woff2", "mp4", "webm", "mp3",
+    "zip", "gz", "br", "pdf",
+];
+
+/// # Chunk size.
+/// The bundle is sent in chunks of this size, as they are zipped.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// # Chunks in flight.
+/// How many chunks can wait for the upload, before the zipping waits too.
+const CHUNKS_IN_FLIGHT: usize = 16;
+
+/// # Default deflate level.
+/// Used by the `auto` policy and by `deflate` without a level, a balance between size and time.
+const DEFAULT_LEVEL: i64 = 6;
+
+/// # Compression policy.
+/// How the files are compressed inside the bundle.
+/// - `stored`: no compression, the fastest to zip.
+/// - `deflate[:level]`: every file deflated, level from 0 to 9.
+/// - `auto`: text files deflated, already compressed files (images, fonts, ...) stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    Stored,
+    Deflate(i64),
+    Auto,
+}
+
+impl Compression {
+    /// # File options for a path.
+    /// Picks the compression method and level for the file, based on the policy.
+    fn options(&self, path: &Path) -> SimpleFileOptions {
+        let options = SimpleFileOptions::default().unix_permissions(0o755);
+
+        match self.level(path) {
+            Some(level) => options
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(level)),
+            None => options.compression_method(CompressionMethod::Stored),
+        }
+    }
+
+    /// # Deflate level for a path.
+    /// The level the file is deflated with, `None` when it's stored.
+    fn level(&self, path: &Path) -> Option<i64> {
+        match self {
+            Compression::Stored => None,
+            Compression::Deflate(level) => Some(*level),
+            Compression::Auto => {
+                let compressed = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| COMPRESSED_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+
+                (!compressed).then_some(DEFAULT_LEVEL)
+            }
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "stored" => Ok(Compression::Stored),
+            None if s == "auto" => Ok(Compression::Auto),
+            None if s == "deflate" => Ok(Compression::Deflate(DEFAULT_LEVEL)),
+            Some(("deflate", level)) => match level.parse::<i64>() {
+                Ok(level) if (0..=9).contains(&level) => Ok(Compression::Deflate(level)),
+                _ => Err(format!("invalid deflate level `{}`, use 0 to 9", level)),
+            },
+            _ => Err(format!(
+                "unknown policy `{}`, use `stored`, `deflate[:level]` or `auto`",
+                s
+            )),
+        }
+    }
+}
+
+/// # Bundle stats.
+/// Sizes of the bundle, used to report how much the compression saved.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BundleStats {
+    pub raw: u64,    // Sum of the zipped files sizes
+    pub zipped: u64, // Bytes written to the bundle
+}
+
+impl fmt::Display for BundleStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ratio = match self.raw {
+            0 => 100.0,
+            raw => self.zipped as f64 / raw as f64 * 100.0,
+        };
+
+        write!(
+            f,
+            "{} -> {} ({:.1}%)",
+            human_size(self.raw),
+            human_size(self.zipped),
+            ratio
+        )
+    }
+}
+
+/// # Human readable size.
+/// Formats a byte count, like `1.2 MB`.
+fn human_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[unit])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// # Channel writer.
+/// Sends the written bytes to the upload, blocking the writer while the upload catches up.
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The upload was dropped."))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// # Upload guard.
+/// Fails the upload unless the bundle is complete, so the builder never gets a truncated bundle that looks whole.
+/// - Dropped while armed (e.g. the zipping panicked), it fails the upload too.
+struct UploadGuard {
+    tx: Option<mpsc::Sender<io::Result<Bytes>>>,
+}
+
+impl UploadGuard {
+    /// # Done
+    /// The bundle is complete, the upload can end.
+    fn done(mut self) {
+        self.tx = None;
+    }
+
+    /// # Fail
+    /// Ends the upload with the zipping error.
+    fn fail(mut self, e: &ZipError) {
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.blocking_send(Err(io::Error::other(e.to_string())));
+        }
+    }
+}
+
+impl Drop for UploadGuard {
+    fn drop(&mut self) {
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.blocking_send(Err(io::Error::other("The zipping stopped unexpectedly.")));
+        }
+    }
+}
+
+/// # Counting writer.
+/// Wraps the channel writer, counting the bytes that go through it.
+struct Counter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
 
 /// # Zip dir, and prepare it to be sent to the builder.
-/// This function will zip the directory, and prepare it to be sent to the builder.
-/// - It will return the zipped directory as a `Vec<u8>`.
+/// This function will zip the directory in a blocking task, and prepare it to be sent to the builder.
+/// - It will return a stream of the zip bytes, available as soon as they are written.
+/// - It will also return the zipping task handle, awaiting it tells if the bundle is complete, and its sizes.
+/// - The content of every zipped file is recorded in the change cache.
 ///
 /// # Examples
 /// ```
 /// let dir = Path::new("test//");
-/// let zip = zip(dir);
+/// let (bundle, zipping) = zip(dir, Filter::new(dir), cache, Compression::Auto)?;
 /// ```
 ///
-/// # Panics
-/// This function will panic if the directory does not exist.
+/// # Errors
+/// This function will fail if the directory does not exist.
 /// Thats because the CLI will not be able to send the directory to the builder.
 /// __That can also be useful for later implementing the `deploy` workflow.__
-#[allow(dead_code)] // While this is not implemented, dead_code suppresses the warning.
-pub fn zip(path: &Path) -> Result<Vec<u8>, ZipError> {
+#[allow(clippy::type_complexity)] // The stream type can't be named.
+pub fn zip(
+    path: &Path,
+    filter: Filter,
+    cache: SharedCache,
+    compression: Compression,
+) -> Result<
+    (
+        impl Stream<Item = io::Result<Bytes>> + Send + Sync + 'static,
+        JoinHandle<Result<BundleStats, ZipError>>,
+    ),
+    ZipError,
+> {
     if !path.is_dir() {
         return Err(ZipError::UnsupportedArchive("The path is not a directory."));
     }
 
-    // ? Create a new zip writer.
-    let walkdir = WalkDir::new(path);
-    let it = walkdir.into_iter();
+    // ? The channel connects the zip writer to the request body, a bounded one so the zipping waits for the upload.
+    let (tx, mut rx) = mpsc::channel(CHUNKS_IN_FLIGHT);
+    let guard = UploadGuard {
+        tx: Some(tx.clone()),
+    };
+    let writer = ChannelWriter { tx };
+    let path: PathBuf = path.to_path_buf();
 
-    // ? Iterate through the files in the directory.
-    Ok(deep_search(&mut it.filter_map(|e| e.ok()), path))
+    let zipping = task::spawn_blocking(move || {
+        // ? Iterate through the files in the directory.
+        let zipped = deep_search(
+            &mut walk(&path, &filter),
+            &path,
+            &cache,
+            compression,
+            writer,
+        );
+
+        // ! The request body only ends cleanly with a complete bundle, an error aborts the upload.
+        match &zipped {
+            Ok(_) => guard.done(),
+            Err(e) => guard.fail(e),
+        }
+
+        zipped
+    });
+
+    let bundle = stream::poll_fn(move |cx| rx.poll_recv(cx));
+
+    Ok((bundle, zipping))
+}
+
+/// # Hash dir.
+/// Reads every file that would be zipped, recording its content in the change cache, without zipping anything.
+/// - Useful to tell if the bundle changed since the last link, before sending it.
+///
+/// # Examples
+/// ```
+/// let dir = Path::new("test//");
+/// hash(dir, &Filter::new(dir), &cache)?;
+/// ```
+pub fn hash(path: &Path, filter: &Filter, cache: &SharedCache) -> io::Result<()> {
+    let mut cache = cache.lock().unwrap();
+
+    for entry in walk(path, filter) {
+        if entry.file_type().is_file() {
+            let buffer = fs::read(entry.path())?;
+            cache.insert(entry.into_path(), &buffer);
+        }
+    }
+
+    Ok(())
+}
+
+/// # Walk dir.
+/// Iterates through the directory entries that are not ignored, sorted so the bundle is always built the same way.
+fn walk<'a>(path: &Path, filter: &'a Filter) -> impl Iterator<Item = DirEntry> + 'a {
+    WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !filter.is_ignored(e.path())) // Ignored directories are not even walked into.
+        .filter_map(|e| e.ok())
 }
 
 /// # Deep search.
-/// This function will iterate through the files in the directory, and zip them.
-/// - It will return the zipped directory as a `Vec<u8>`.
-/// - It will return an empty vector if the directory is empty.
+/// This function will iterate through the files in the directory, and zip them into the writer.
+/// - It will write an empty archive if the directory is empty.
+/// - It will return the raw and zipped sizes of the bundle.
 ///
 /// # Examples
 /// ```
 /// let dir = Path::new("test//");
-/// let zip = deep_search(&mut it.filter_map(|e| e.ok()), path);
+/// let stats = deep_search(&mut it.filter_map(|e| e.ok()), path, &cache, Compression::Stored, writer)?;
 /// ```
-fn deep_search(it: &mut dyn Iterator<Item = DirEntry>, prefix: &Path) -> Vec<u8> {
-    // ? Create a new zip writer.
-    let mut buf = Vec::new(); // Responsible for handling the buffer and the bytes in it.
-    let mut cursor = Cursor::new(&mut buf); // Responsible for the cursor.
-    let mut zip = ZipWriter::new(&mut cursor); // Responsible for the zip writer.
+fn deep_search(
+    it: &mut dyn Iterator<Item = DirEntry>,
+    prefix: &Path,
+    cache: &SharedCache,
+    compression: Compression,
+    writer: impl Write,
+) -> Result<BundleStats, ZipError> {
+    // ? Create a new zip writer, streamed as it doesn't need to seek back.
+    let mut zip = ZipWriter::new_stream(BufWriter::with_capacity(
+        CHUNK_SIZE,
+        Counter {
+            inner: writer,
+            count: 0,
+        },
+    ));
 
-    // ? Options for the file to be added to the zip archive.
-    let options = FileOptions::default()
-        .compression_method(zip::CompressionMethod::Stored)
-        .unix_permissions(0o755);
+    let mut stats = BundleStats::default();
 
     // ? Buffer for files inside the directories.
     let mut buffer = Vec::new();
@@ -67,58 +351,48 @@ fn deep_search(it: &mut dyn Iterator<Item = DirEntry>, prefix: &Path) -> Vec<u8>
     for entry in it {
         let path = entry.path(); // Get the file path
 
-        // ? Check if the path is different than a list of files to ignore
-        let ignore = vec!["node_modules"]; // TODO: get this from the .vtexignore file
+        // ? Path inside the archive.
+        let name = path.strip_prefix(prefix).map_err(io::Error::other)?;
 
-        if ignore.iter().any(|x| path.to_str().unwrap().contains(x)) {
-            continue;
-        }
-
-        // ? Check if the path is a directory.
-        let name = path.strip_prefix(prefix).unwrap();
-
-        // Write file or directory explicitly
-        // Some unzip tools unzip files with directory paths correctly, some do not!
+        // Only files are written, their paths already carry the directories.
+        // !!! Explicit directory entries are skipped, in a streamed zip they are flagged with a data descriptor that is never written, and unzip tools reject the archive.
         if path.is_file() {
             // ? Thats definitely not the beauty way to do this, but it works.
             // !!! The zip writer in windows devices, uses \\ to separate directories, but when handling it on linux, it uses /, this creates a problem, here we replace it.
-            let mut p = str::replace(name.to_str().unwrap(), "\\", "/"); // Replace the backslashes with slashes.
+            let name = name.to_str().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is not a valid UTF-8 path.", path.display()),
+                )
+            })?;
+            let mut p = str::replace(name, "\\", "/"); // Replace the backslashes with slashes.
             if p.starts_with('/') {
                 p.remove(0);
             } // Remove the first '/'
 
-            // ? Write the file to the buffer.
-            zip.start_file(p, options).unwrap();
-            let mut f = File::open(path).unwrap();
+            // ? Write the file to the buffer, compressed as the policy says.
+            zip.start_file(p, compression.options(path))?;
+            let mut f = File::open(path)?;
 
             /* // ? Minify the file.
             // TODO: Think about minification, size reduction x timing.
             minify(path, &mut f, &mut buffer); // !!! Apparently its not working, for JSX & TSX files. */
 
-            f.read_to_end(&mut buffer).unwrap();
-            zip.write_all(&buffer).unwrap();
+            f.read_to_end(&mut buffer)?;
+            zip.write_all(&buffer)?;
+            stats.raw += buffer.len() as u64;
+            cache.lock().unwrap().insert(path.to_path_buf(), &buffer); // Seed the change cache.
             buffer.clear();
-        } else if !name.as_os_str().is_empty() {
-            // Only if not root! Avoids path spec / warning
-            // and mapname conversion failed error on unzip
-
-            // * Thats definitely not the beauty way to do this, but it works.
-            // ? The zip writer in windows devices, uses \\ to separate directories, but when handling it on linux, it uses /, this creates a problem, here we replace it.
-            let mut p = str::replace(name.to_str().unwrap(), "\\", "/"); // Replace the backslashes with slashes.
-            if p.starts_with('/') {
-                p.remove(0);
-            } // Remove the first '/' if exists.
-
-            // ? Write the file to the buffer.
-            zip.add_directory(p, options).unwrap();
         }
     }
 
-    // ? Finish the zip archive.
-    zip.finish().unwrap(); // Close the file in the zip archive
-    drop(zip); // !!! We drop zip here to deallocate `buf`, so we can return the buffer without returning function lifeline values.
+    // ? Finish the zip archive, and flush whatever is left in the buffer to the upload.
+    let mut writer = zip.finish()?.into_inner();
+    writer.flush()?;
+
+    stats.zipped = writer.get_ref().count;
 
-    buf // * Return the buffer, witch has the bytes of the zip file.
+    Ok(stats) // * Dropping the writer (and the guard) closes the channel, ending the request body.
 }
 
 /// # Minify the file.
@@ -174,3 +448,47 @@ fn minify(path: &Path, f: &mut File, buffer: &mut Vec<u8>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_compression_policy() {
+        assert_eq!("stored".parse(), Ok(Compression::Stored));
+        assert_eq!("auto".parse(), Ok(Compression::Auto));
+        assert_eq!("deflate".parse(), Ok(Compression::Deflate(DEFAULT_LEVEL)));
+        assert_eq!("deflate:0".parse(), Ok(Compression::Deflate(0)));
+        assert_eq!("deflate:9".parse(), Ok(Compression::Deflate(9)));
+
+        assert!("deflate:10".parse::<Compression>().is_err());
+        assert!("deflate:fast".parse::<Compression>().is_err());
+        assert!("stored:1".parse::<Compression>().is_err());
+        assert!("brotli".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn stores_the_compressed_files_with_auto() {
+        let level = |compression: Compression, path: &str| compression.level(Path::new(path));
+
+        assert_eq!(
+            level(Compression::Auto, "react/index.tsx"),
+            Some(DEFAULT_LEVEL)
+        );
+        assert_eq!(level(Compression::Auto, "Makefile"), Some(DEFAULT_LEVEL));
+        assert_eq!(level(Compression::Auto, "assets/logo.PNG"), None);
+        assert_eq!(level(Compression::Auto, "fonts/inter.woff2"), None);
+        assert_eq!(level(Compression::Stored, "react/index.tsx"), None);
+        assert_eq!(level(Compression::Deflate(1), "assets/logo.png"), Some(1));
+    }
+
+    #[test]
+    fn formats_the_bundle_stats() {
+        let stats = BundleStats {
+            raw: 2 * 1024 * 1024,
+            zipped: 512 * 1024,
+        };
+        assert_eq!(stats.to_string(), "2.0 MB -> 512.0 KB (25.0%)");
+        assert_eq!(BundleStats::default().to_string(), "0 B -> 0 B (100.0%)");
+    }
+}
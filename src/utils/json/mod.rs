@@ -1,19 +1,16 @@
 //! # Read the JSON file.
 //! Read the JSON file and deserialize it.
 //!
-//! # Panics
-//! This function will panic if the JSON file is not properly formatted.
+//! # Errors
+//! The parsing error is returned if the JSON file is not properly formatted.
 
 use serde::de;
 use std::{fs::File, io::BufReader};
 
 /// # Read the JSON file.
 /// Read the JSON file and deserialize it.
-///
-/// # Panics
-/// This function will panic if the JSON file is not properly formatted.
-#[allow(dead_code)] // While this is not implemented, dead_code suppresses the warning.
-pub fn read<T>(file: File) -> Result<T, ()>
+/// - It will return the parsing error, for the caller to tell what the file was.
+pub fn read<T>(file: File) -> Result<T, serde_json::Error>
 where
     T: de::DeserializeOwned,
 {
@@ -21,10 +18,5 @@ where
     let reader = BufReader::new(file);
 
     // ? Deserializes the JSON
-    match serde_json::from_reader(reader) {
-        // * Deserialization successful
-        Ok(data) => Ok(data),
-        // !!! Wasn't able to deserialize the JSON file
-        Err(e) => Err(error!("JSON Parsing failed during read: {:?}", e)),
-    }
+    serde_json::from_reader(reader)
 }
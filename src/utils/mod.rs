@@ -10,6 +10,18 @@ pub mod b64;
 /// This module contains functions to compress files into bytes.
 pub mod gzip;
 
+/// # Mod to handle the project ignore rules
+/// This module contains the filter used to skip files, both when bundling and watching.
+pub mod filter;
+
+/// # Mod to handle the content hashes of the sent files
+/// This module contains the cache used to skip uploads of unchanged files.
+pub mod cache;
+
+/// # Mod to handle the build progress display
+/// This module contains the live view of the builders running during a link.
+pub mod progress;
+
 // ? Debug zip file:
 /* {
     debug!("Write: {:?}", file);
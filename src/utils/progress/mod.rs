@@ -0,0 +1,304 @@
+//! # Build progress
+//! A live view of the builds during a link session, which builders are running and for how long.
+//! - On a terminal, each build in progress has a line redrawn in place, until it ends. A log line clears them first.
+//! - Otherwise (e.g. piped to a file), each step is a plain log line.
+//! - When several apps are linked, each app has its own build, labeled with the app.
+//!
+//! # Examples
+//! ```rust
+//! let mut progress = Progress::new(ctx.builders.keys().cloned());
+//! progress.start(None, vec![]); // Every builder in the manifest.
+//! progress.tick(); // ⠙ Building react 1.2s, store 1.2s
+//! progress.finish(None, timings); // Linked in 4.2s (react 3.1s, store 0.4s)
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    io::{self, IsTerminal},
+    mem,
+    time::{Duration, Instant},
+};
+
+use crate::output::{self, Level};
+
+/// # Spinner frames.
+const FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// # Progress struct.
+/// The builds in progress, one per app.
+#[derive(Debug)]
+pub struct Progress {
+    builders: Vec<String>, // Builders in the manifests, when the build doesn't tell
+    builds: BTreeMap<String, Build>, // App => its build, `""` for a single app link
+    frame: usize,
+    tty: bool,
+}
+
+/// # Build struct.
+/// The builders of an app build, running or done.
+#[derive(Debug, Default)]
+struct Build {
+    started: Option<Instant>, // When the build started, `None` if it started before the session did
+    running: BTreeMap<String, Instant>, // Builder name => when it started
+    done: BTreeMap<String, Duration>, // Builder name => how long it took
+}
+
+/// # Implements the `Progress` and define the **start**, **finish**, **fail**, **stop** and **tick** methods.
+impl Progress {
+    pub fn new(builders: impl IntoIterator<Item = String>) -> Progress {
+        Progress {
+            builders: builders.into_iter().collect(),
+            builds: BTreeMap::new(),
+            frame: 0,
+            tty: io::stdout().is_terminal() && output::enabled(Level::Info), // Nothing is redrawn in JSON or quiet mode
+        }
+    }
+
+    /// # Start a build.
+    /// A new build of an app replaces the one in progress, e.g. a relink sent during a build.
+    /// - The `app` labels the build, `None` for a single app link.
+    pub fn start(&mut self, app: Option<String>, builders: Vec<String>) {
+        let builders = if builders.is_empty() {
+            self.builders.clone()
+        } else {
+            builders
+        };
+
+        let now = Instant::now();
+        let app = app.unwrap_or_default();
+        let build = Build {
+            started: Some(now),
+            running: builders.into_iter().map(|builder| (builder, now)).collect(),
+            done: BTreeMap::new(),
+        };
+
+        if self.tty {
+            self.builds.insert(app, build);
+            self.draw();
+        } else {
+            let names: Vec<&str> = build.running.keys().map(String::as_str).collect();
+            info!("🔨 Building {}{}...", label(&app), names.join(", "));
+            self.builds.insert(app, build);
+        }
+    }
+
+    /// # Finish a build.
+    /// A successful build of `app` ends here, whether or not `timings` covers every running builder.
+    /// - The builders in `timings` took that long, the others are timed with the local clock.
+    pub fn finish(&mut self, app: Option<String>, timings: Vec<(String, Duration)>) {
+        let app = app.unwrap_or_default();
+        let mut build = self.builds.remove(&app).unwrap_or_default();
+
+        for (builder, took) in timings {
+            build.running.remove(&builder);
+            build.done.insert(builder, took);
+        }
+
+        // ? No timings for those, the local clock is the best guess.
+        for (builder, started) in mem::take(&mut build.running) {
+            build.done.insert(builder, started.elapsed());
+        }
+
+        // ? The build may have started before the session did, then the slowest builder is the total.
+        let total = match build.started {
+            Some(started) => started.elapsed(),
+            None => build.done.values().max().copied().unwrap_or_default(),
+        };
+
+        success!("{}", summary(&app, &build, total));
+        self.redraw();
+    }
+
+    /// # Fail a build.
+    /// Ends the build of `app`, it will return how long it ran.
+    pub fn fail(&mut self, app: Option<String>) -> Option<Duration> {
+        let took = self
+            .builds
+            .remove(&app.unwrap_or_default())
+            .and_then(|build| build.started)
+            .map(|started| started.elapsed());
+
+        self.redraw();
+        took
+    }
+
+    /// # Stop every build.
+    /// Ends the builds of every app, e.g. when the link is interrupted.
+    pub fn stop(&mut self) {
+        self.builds.clear();
+        self.redraw();
+    }
+
+    /// # Ticking
+    /// Whether the live lines need redrawing, only on a terminal and during a build.
+    pub fn ticking(&self) -> bool {
+        self.tty && !self.builds.is_empty()
+    }
+
+    /// # Tick
+    /// Redraws the live lines with the next spinner frame, only on a terminal and during a build.
+    pub fn tick(&mut self) {
+        if self.ticking() {
+            self.frame = (self.frame + 1) % FRAMES.len();
+            self.draw();
+        }
+    }
+
+    /// # Redraw
+    /// Draws the builds still in progress once one ends, or clears the live lines if none is.
+    fn redraw(&mut self) {
+        if self.ticking() {
+            self.draw();
+        } else {
+            output::clear_live();
+        }
+    }
+
+    /// # Draw
+    /// Draws a live line per build in place, e.g. `⠙ Building vendor.app: react 1.2s, store ✔ 0.4s`.
+    fn draw(&mut self) {
+        let lines: Vec<String> = self
+            .builds
+            .iter()
+            .map(|(app, build)| {
+                let mut builders: Vec<String> = build
+                    .running
+                    .iter()
+                    .map(|(builder, started)| format!("{} {}", builder, secs(started.elapsed())))
+                    .collect();
+                builders.extend(
+                    build
+                        .done
+                        .iter()
+                        .map(|(builder, took)| format!("{} ✔ {}", builder, secs(*took))),
+                );
+
+                format!(
+                    "{} Building {}{}",
+                    FRAMES[self.frame],
+                    label(app),
+                    builders.join(", ")
+                )
+            })
+            .collect();
+
+        output::draw_live(&lines);
+    }
+}
+
+/// # Label
+/// The app building, e.g. `vendor.app: `, empty for a single app link.
+fn label(app: &str) -> String {
+    if app.is_empty() {
+        String::new()
+    } else {
+        format!("{}: ", app)
+    }
+}
+
+/// # Summary
+/// The line logged once a build ends, e.g. `Linked in 4.2s (react 3.1s, store 0.4s)`.
+fn summary(app: &str, build: &Build, total: Duration) -> String {
+    let builders: Vec<String> = build
+        .done
+        .iter()
+        .map(|(builder, took)| format!("{} {}", builder, secs(*took)))
+        .collect();
+
+    let linked = if app.is_empty() {
+        "Linked".to_string()
+    } else {
+        format!("{} linked", app)
+    };
+
+    if builders.is_empty() {
+        format!("{} in {}", linked, secs(total))
+    } else {
+        format!("{} in {} ({})", linked, secs(total), builders.join(", "))
+    }
+}
+
+/// # Seconds
+/// A duration as seconds, with a single decimal.
+fn secs(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress() -> Progress {
+        let mut progress = Progress::new(["react".to_string(), "store".to_string()]);
+        progress.tty = false;
+        progress
+    }
+
+    #[test]
+    fn ends_the_build_on_success() {
+        let mut progress = progress();
+        progress.start(None, vec![]);
+        assert_eq!(progress.builds[""].running.len(), 2);
+
+        // ? The builder only timed react, store is done all the same.
+        progress.finish(None, vec![("react".to_string(), Duration::from_secs(3))]);
+        assert!(progress.builds.is_empty());
+    }
+
+    #[test]
+    fn ends_the_build_on_failure() {
+        let mut progress = progress();
+        progress.start(Some("vendor.app".to_string()), vec!["react".to_string()]);
+
+        assert!(progress.fail(Some("vendor.app".to_string())).is_some());
+        assert!(progress.builds.is_empty());
+        assert!(progress.fail(Some("vendor.app".to_string())).is_none());
+    }
+
+    #[test]
+    fn keeps_a_build_per_app() {
+        let mut progress = progress();
+        progress.start(Some("vendor.a".to_string()), vec!["react".to_string()]);
+        progress.start(Some("vendor.b".to_string()), vec!["node".to_string()]);
+        assert_eq!(progress.builds.len(), 2);
+
+        // ? One app ending its build leaves the other one running.
+        progress.finish(Some("vendor.a".to_string()), vec![]);
+        assert_eq!(progress.builds.keys().collect::<Vec<_>>(), ["vendor.b"]);
+        assert!(progress.builds["vendor.b"].running.contains_key("node"));
+
+        progress.start(Some("vendor.a".to_string()), vec![]);
+        progress.stop();
+        assert!(progress.builds.is_empty());
+    }
+
+    #[test]
+    fn ticks_only_during_a_build_on_a_terminal() {
+        let mut progress = progress();
+        progress.start(None, vec![]);
+        assert!(!progress.ticking());
+
+        progress.tty = true;
+        assert!(progress.ticking());
+
+        progress.finish(None, vec![]);
+        assert!(!progress.ticking());
+    }
+
+    #[test]
+    fn summarizes_the_build() {
+        let build = Build {
+            done: BTreeMap::from([("react".to_string(), Duration::from_millis(3100))]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            summary("vendor.app", &build, Duration::from_millis(4200)),
+            "vendor.app linked in 4.2s (react 3.1s)"
+        );
+        assert_eq!(
+            summary("", &Build::default(), Duration::from_millis(4200)),
+            "Linked in 4.2s"
+        );
+    }
+}
@@ -0,0 +1,1258 @@
+//! # Link end-to-end tests
+//! Runs `quix link` (and `quix unlink`) against the mock builder-hub, offline, in a throwaway home and project.
+
+mod mock;
+
+use std::{
+    env, fs,
+    io::Cursor,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use mock::{MockHub, Request};
+use serde_json::Value;
+
+/// # Timeout
+/// How long to wait for a request, generous as the watcher debounces the changes.
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// # Sandbox struct
+/// A home with a VTEX session, and a project to link, removed on drop along with the running quix.
+struct Sandbox {
+    root: PathBuf,
+    child: Option<Child>,
+    envs: Vec<(&'static str, String)>, // Extra environment of quix
+    cwd: PathBuf,                      // Where quix runs, the project by default
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Sandbox {
+        let root = env::temp_dir().join(format!("quix-e2e-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let configstore = root.join("home/.config/configstore");
+        fs::create_dir_all(&configstore).unwrap();
+        fs::write(
+            configstore.join("vtex.json"),
+            r#"{"account":"mock","login":"quix@mock.com","token":"mock-token","workspace":"e2e","_lastUsedWorkspace":"master","apps":{}}"#,
+        )
+        .unwrap();
+
+        let project = root.join("project");
+        fs::create_dir_all(project.join("react")).unwrap();
+        fs::create_dir_all(project.join("docs")).unwrap();
+        fs::write(
+            project.join("manifest.json"),
+            r#"{"vendor":"quix","name":"e2e","version":"0.0.1","builders":{"react":"3.x"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            project.join("react/index.tsx"),
+            "export default () => null\n",
+        )
+        .unwrap();
+        fs::write(project.join("docs/README.md"), "# Not linked\n").unwrap();
+
+        Sandbox {
+            root,
+            child: None,
+            envs: vec![],
+            cwd: project,
+        }
+    }
+
+    fn project(&self) -> PathBuf {
+        self.root.join("project")
+    }
+
+    /// # Link
+    /// Starts `quix link` in the project, pointed at the mock.
+    fn link(&mut self, hub: &MockHub, args: &[&str]) {
+        self.run(hub, "link", args)
+    }
+
+    /// # Run
+    /// Starts a quix subcommand in the project, pointed at the mock.
+    fn run(&mut self, hub: &MockHub, subcommand: &str, args: &[&str]) {
+        let child = Command::new(env!("CARGO_BIN_EXE_quix"))
+            .arg(subcommand)
+            .args(args)
+            .current_dir(&self.cwd)
+            .env("HOME", self.root.join("home"))
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("QUIX_CONFIGSTORE")
+            .env("QUIX_BUILDER_URL", &hub.url)
+            .env("QUIX_COLOSSUS_URL", &hub.url)
+            .envs(self.envs.iter().cloned())
+            .stdout(fs::File::create(self.root.join("quix.log")).unwrap())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        self.child = Some(child);
+    }
+
+    /// # Output
+    /// Everything quix printed so far.
+    fn output(&self) -> String {
+        fs::read_to_string(self.root.join("quix.log")).unwrap_or_default()
+    }
+
+    /// # Interrupt
+    /// Sends a Ctrl-C (SIGINT) to the running quix.
+    fn interrupt(&self) {
+        let pid = self.child.as_ref().unwrap().id().to_string();
+        let status = Command::new("kill").args(["-INT", &pid]).status().unwrap();
+        assert!(status.success(), "could not interrupt quix");
+    }
+
+    /// # Exit code
+    /// Waits for the running quix to exit, returning its exit code.
+    fn exit_code(&mut self) -> Option<i32> {
+        let child = self.child.as_mut()?;
+        let start = Instant::now();
+
+        while start.elapsed() < TIMEOUT {
+            if let Some(status) = child.try_wait().unwrap() {
+                return status.code();
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        None
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// # Bundle files
+/// The file names inside a linked bundle.
+fn bundle_files(request: &Request) -> Vec<String> {
+    let archive = zip::ZipArchive::new(Cursor::new(request.body.clone())).unwrap();
+    archive.file_names().map(String::from).collect()
+}
+
+/// # Relinked paths
+/// The paths sent in a relink body.
+fn relinked_paths(request: &Request) -> Vec<String> {
+    let files: Vec<Value> = serde_json::from_slice(&request.body).unwrap();
+    files
+        .iter()
+        .filter_map(|file| file["path"].as_str().map(String::from))
+        .collect()
+}
+
+/// # Removed paths
+/// The paths a relink body removes, sent without content.
+fn removed_paths(request: &Request) -> Vec<String> {
+    let files: Vec<Value> = serde_json::from_slice(&request.body).unwrap();
+    files
+        .iter()
+        .filter(|file| file["content"].is_null())
+        .filter_map(|file| file["path"].as_str().map(String::from))
+        .collect()
+}
+
+/// # Wait for the watcher
+/// Writes a file until its relink comes, so the next changes are surely seen.
+fn wait_for_the_watcher(hub: &MockHub, sandbox: &Sandbox) {
+    let start = Instant::now();
+    loop {
+        fs::write(
+            sandbox.project().join("react/index.tsx"),
+            format!("export default () => {}\n", start.elapsed().as_millis()),
+        )
+        .unwrap();
+
+        if hub
+            .wait_for(|r| r.route() == "relink", Duration::from_secs(3))
+            .is_some()
+        {
+            return;
+        }
+        assert!(start.elapsed() < TIMEOUT, "the watcher never started");
+    }
+}
+
+#[test]
+fn link_sends_the_bundle_to_the_sticky_host() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("bundle");
+    sandbox.link(&hub, &[]);
+
+    let link = hub
+        .wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    assert_eq!(link.method, "POST");
+    assert!(link
+        .path
+        .contains("/vtex.builder-hub/v0/mock/e2e/_v/builder/0/link/quix.e2e@0.0.1"));
+    assert_eq!(link.header("authorization"), Some("Bearer mock-token"));
+    assert_eq!(link.header("x-vtex-sticky-host"), Some("mock-builder"));
+
+    let mut files = bundle_files(&link);
+    files.sort();
+    assert_eq!(files, ["manifest.json", "react/index.tsx"]);
+
+    assert_eq!(hub.count("availability"), 1);
+}
+
+#[test]
+fn link_keeps_the_other_apps_in_the_configstore() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("configstore");
+    let configstore = sandbox.root.join("home/.config/configstore");
+    fs::write(
+        configstore.join("vtex.json"),
+        r#"{"account":"mock","login":"quix@mock.com","token":"mock-token","workspace":"e2e","_lastUsedWorkspace":"master","env":"prod",
+        "apps":{"other":{"app":{"sticky-host":{"stickyHost":"other-builder","lastUpdated":1}}},"quix":{"theme":{"sticky-host":{"stickyHost":"theme-builder","lastUpdated":2}}}}}"#,
+    )
+    .unwrap();
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    // ? The new entry is merged in, next to the ones of the other apps and the rest of the toolbelt data.
+    let vtex: Value =
+        serde_json::from_str(&fs::read_to_string(configstore.join("vtex.json")).unwrap()).unwrap();
+    let apps = &vtex["apps"];
+    assert_eq!(
+        apps["quix"]["e2e"]["sticky-host"]["stickyHost"],
+        "mock-builder"
+    );
+    assert_eq!(
+        apps["quix"]["theme"]["sticky-host"]["stickyHost"],
+        "theme-builder"
+    );
+    assert_eq!(
+        apps["other"]["app"]["sticky-host"]["stickyHost"],
+        "other-builder"
+    );
+    assert_eq!(vtex["env"], "prod");
+    assert_eq!(vtex["token"], "mock-token");
+
+    // ? Nothing but the store and its lock is left behind.
+    let mut files: Vec<String> = fs::read_dir(&configstore)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, [".vtex.json.lock", "vtex.json"]);
+}
+
+#[test]
+fn link_finds_the_configstore_elsewhere() {
+    for (setup, vtex) in [
+        ("xdg", "xdg/configstore/vtex.json"),
+        ("env", "env/vtex.json"),
+        ("flag", "flag/session.json"),
+    ] {
+        let hub = MockHub::start();
+        let mut sandbox = Sandbox::new(&format!("configstore-{}", setup));
+
+        // ? Moved out of `~/.config`, where quix would look by default.
+        let path = sandbox.root.join(vtex);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::rename(
+            sandbox.root.join("home/.config/configstore/vtex.json"),
+            &path,
+        )
+        .unwrap();
+
+        let flag = path.to_string_lossy().into_owned();
+        let args = match setup {
+            "xdg" => {
+                let xdg = sandbox.root.join("xdg");
+                sandbox
+                    .envs
+                    .push(("XDG_CONFIG_HOME", xdg.to_string_lossy().into()));
+                vec![]
+            }
+            "env" => {
+                let dir = sandbox.root.join("env");
+                sandbox
+                    .envs
+                    .push(("QUIX_CONFIGSTORE", dir.to_string_lossy().into()));
+                vec![]
+            }
+            _ => vec!["--config", flag.as_str()],
+        };
+        sandbox.link(&hub, &args);
+
+        let link = hub
+            .wait_for(|r| r.route() == "link", TIMEOUT)
+            .unwrap_or_else(|| panic!("{}: the bundle was never sent", setup));
+        assert_eq!(link.header("authorization"), Some("Bearer mock-token"));
+
+        // ? The sticky host is stored next to the session.
+        let vtex: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            vtex["apps"]["quix"]["e2e"]["sticky-host"]["stickyHost"], "mock-builder",
+            "{}",
+            setup
+        );
+    }
+}
+
+#[test]
+fn link_relinks_the_changed_files() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("relink");
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    // ? Give the watcher a moment to start, then change a linked and an ignored file.
+    let project = sandbox.project();
+    let start = Instant::now();
+    let relink = loop {
+        fs::write(project.join("react/index.tsx"), "export default () => 42\n").unwrap();
+        fs::write(project.join("docs/README.md"), "# Still not linked\n").unwrap();
+
+        if let Some(relink) = hub.wait_for(|r| r.route() == "relink", Duration::from_secs(3)) {
+            break relink;
+        }
+        assert!(start.elapsed() < TIMEOUT, "the change was never relinked");
+    };
+
+    assert_eq!(relink.method, "PUT");
+    assert_eq!(relinked_paths(&relink), ["react/index.tsx"]);
+}
+
+#[test]
+fn link_removes_a_deleted_file() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("delete");
+    fs::write(sandbox.project().join("react/gone.tsx"), "export {}\n").unwrap();
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    wait_for_the_watcher(&hub, &sandbox);
+
+    fs::remove_file(sandbox.project().join("react/gone.tsx")).unwrap();
+
+    let removal = hub
+        .wait_for(
+            |r| r.route() == "relink" && !removed_paths(r).is_empty(),
+            TIMEOUT,
+        )
+        .expect("the removal was never sent");
+    assert_eq!(removed_paths(&removal), ["react/gone.tsx"]);
+}
+
+#[test]
+fn link_relinks_through_the_churn_of_ignored_files() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("churn");
+    let modules = sandbox.project().join("node_modules");
+    fs::create_dir_all(&modules).unwrap();
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    wait_for_the_watcher(&hub, &sandbox);
+    let relinks = hub.count("relink");
+
+    // ? An ignored file rewritten faster than the debounce window can't hold the change back.
+    fs::write(sandbox.project().join("react/churn.tsx"), "export {}\n").unwrap();
+    let start = Instant::now();
+    while hub.count("relink") == relinks {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "the change was never relinked"
+        );
+        fs::write(
+            modules.join("cache.js"),
+            start.elapsed().as_millis().to_string(),
+        )
+        .unwrap();
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn link_sends_a_change_again_after_a_failed_relink() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("relinkfail");
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    wait_for_the_watcher(&hub, &sandbox);
+
+    // ? Both the relink and its retry on the resolved host fail.
+    hub.fail_next("relink", 503);
+    hub.fail_next("relink", 503);
+    let sent = |hub: &MockHub| {
+        hub.requests()
+            .iter()
+            .filter(|r| r.route() == "relink" && relinked_paths(r) == ["react/retry.tsx"])
+            .count()
+    };
+
+    let path = sandbox.project().join("react/retry.tsx");
+    fs::write(&path, "export default 1\n").unwrap();
+    let start = Instant::now();
+    while sent(&hub) < 2 || !sandbox.output().contains("503") {
+        assert!(start.elapsed() < TIMEOUT, "the relink never failed");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // ? Saving the same content again, the builder never got it.
+    fs::write(&path, "export default 1\n").unwrap();
+    let start = Instant::now();
+    while sent(&hub) < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the change was never sent again");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn link_moves_the_files_of_a_renamed_directory() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("rename");
+    let components = sandbox.project().join("react/components");
+    fs::create_dir_all(&components).unwrap();
+    fs::write(components.join("Button.tsx"), "export {}\n").unwrap();
+    fs::write(components.join("Card.tsx"), "export {}\n").unwrap();
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    wait_for_the_watcher(&hub, &sandbox);
+
+    fs::rename(&components, sandbox.project().join("react/widgets")).unwrap();
+
+    // ? Each file under the directory goes away, and comes back under the new name.
+    let start = Instant::now();
+    let (mut removed, mut written) = (vec![], vec![]);
+    while removed.len() < 2 || written.len() < 2 {
+        assert!(start.elapsed() < TIMEOUT, "the rename was never relinked");
+        thread::sleep(Duration::from_millis(50));
+
+        let relinks: Vec<Request> = hub
+            .requests()
+            .into_iter()
+            .filter(|r| r.route() == "relink")
+            .collect();
+        removed = relinks.iter().flat_map(removed_paths).collect();
+        written = relinks
+            .iter()
+            .flat_map(relinked_paths)
+            .filter(|path| path.starts_with("react/widgets"))
+            .collect();
+    }
+
+    removed.sort();
+    written.sort();
+    assert_eq!(
+        removed,
+        ["react/components/Button.tsx", "react/components/Card.tsx"]
+    );
+    assert_eq!(
+        written,
+        ["react/widgets/Button.tsx", "react/widgets/Card.tsx"]
+    );
+}
+
+#[test]
+fn link_batches_the_changes_of_a_window() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("batch");
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    // ? A name that has to be escaped in the JSON body.
+    let files = ["react/a.tsx", "react/b.tsx", r#"react/say "hi" \ bye.tsx"#];
+    let project = sandbox.project();
+    let start = Instant::now();
+    let relink = loop {
+        let round = start.elapsed().as_millis();
+        for file in files {
+            fs::write(project.join(file), format!("export default {}\n", round)).unwrap();
+        }
+
+        let batched = |r: &Request| r.route() == "relink" && relinked_paths(r).len() == files.len();
+        if let Some(relink) = hub.wait_for(batched, Duration::from_secs(3)) {
+            break relink;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "the changes were never relinked together"
+        );
+    };
+
+    let mut paths = relinked_paths(&relink);
+    paths.sort();
+    assert_eq!(paths, files);
+}
+
+#[test]
+fn link_skips_a_web_app_manifest_above_the_cwd() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("webmanifest");
+    let project = sandbox.project();
+    fs::create_dir_all(project.join("public")).unwrap();
+    fs::write(
+        project.join("public/manifest.json"),
+        r#"{"name":"Store","short_name":"Store","display":"standalone"}"#,
+    )
+    .unwrap();
+    sandbox.cwd = project.join("public");
+    sandbox.link(&hub, &[]);
+
+    // ? The app above `public/` is the one linked.
+    let link = hub
+        .wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    assert!(link.path.contains("/link/quix.e2e@0.0.1"), "{}", link.path);
+    assert!(bundle_files(&link).contains(&"manifest.json".to_string()));
+}
+
+#[test]
+fn link_works_from_a_subdirectory() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("subdir");
+    let project = sandbox.project();
+    fs::create_dir_all(project.join("react/components")).unwrap();
+    sandbox.cwd = project.join("react/components");
+    sandbox.link(&hub, &[]);
+
+    // ? The whole project is bundled, not only the directory quix runs in.
+    let link = hub
+        .wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    let mut files = bundle_files(&link);
+    files.sort();
+    assert_eq!(files, ["manifest.json", "react/index.tsx"]);
+
+    // ? And the paths are relative to the project root.
+    let start = Instant::now();
+    let relink = loop {
+        fs::write(
+            project.join("react/components/Button.tsx"),
+            "export default () => null\n",
+        )
+        .unwrap();
+
+        if let Some(relink) = hub.wait_for(|r| r.route() == "relink", Duration::from_secs(3)) {
+            break relink;
+        }
+        assert!(start.elapsed() < TIMEOUT, "the change was never relinked");
+    };
+    assert_eq!(relinked_paths(&relink), ["react/components/Button.tsx"]);
+}
+
+/// # Monorepo
+/// Adds a second app to the sandbox project, and a web app manifest that isn't an app.
+fn monorepo(sandbox: &Sandbox) -> PathBuf {
+    let second = sandbox.project().join("apps/second");
+    fs::create_dir_all(second.join("react")).unwrap();
+    fs::write(
+        second.join("manifest.json"),
+        r#"{"vendor":"quix","name":"second","version":"0.0.1","builders":{"react":"3.x"}}"#,
+    )
+    .unwrap();
+    fs::write(second.join("react/index.tsx"), "export default () => 2\n").unwrap();
+
+    let public = sandbox.project().join("public");
+    fs::create_dir_all(&public).unwrap();
+    fs::write(public.join("manifest.json"), r#"{"name":"PWA","icons":[]}"#).unwrap();
+
+    second
+}
+
+/// # Links of an app
+/// How many bundles of the app were sent.
+fn links_of(hub: &MockHub, app: &str) -> usize {
+    hub.requests()
+        .iter()
+        .filter(|r| r.route() == "link" && r.path.contains(app))
+        .count()
+}
+
+#[test]
+fn link_links_every_app_in_a_monorepo() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("monorepo");
+    let second = monorepo(&sandbox);
+    sandbox.link(&hub, &["--all"]);
+
+    // ? Each app is sent to its own builder, and they share the streams.
+    let start = Instant::now();
+    while hub.count("link") < 2 || hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the apps were never linked");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(links_of(&hub, "quix.e2e@0.0.1"), 1);
+    assert_eq!(links_of(&hub, "quix.second@0.0.1"), 1);
+    assert_eq!(hub.count("availability"), 2);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(hub.streams(), 3);
+
+    // ? A change goes to the app holding it, relative to its root.
+    let start = Instant::now();
+    let relink = loop {
+        fs::write(second.join("react/index.tsx"), "export default () => 22\n").unwrap();
+
+        if let Some(relink) = hub.wait_for(|r| r.route() == "relink", Duration::from_secs(3)) {
+            break relink;
+        }
+        assert!(start.elapsed() < TIMEOUT, "the change was never relinked");
+    };
+    assert!(relink.path.contains("quix.second@0.0.1"), "{}", relink.path);
+    assert_eq!(relinked_paths(&relink), ["react/index.tsx"]);
+
+    // ? Only the app the builder asks for is sent again, never one outside the session.
+    hub.emit_to(
+        &["events"],
+        r#"{"subject":"other.app@1.0.0","body":{"code":"initial_link_required"}}"#,
+    );
+    hub.emit_to(
+        &["events"],
+        r#"{"subject":"quix.second@0.0.1","body":{"code":"initial_link_required"}}"#,
+    );
+    let start = Instant::now();
+    while links_of(&hub, "quix.second@0.0.1") < 2 {
+        assert!(start.elapsed() < TIMEOUT, "the bundle was never sent again");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(links_of(&hub, "quix.e2e@0.0.1"), 1);
+
+    // ? The logs tell which app they came from.
+    hub.emit_to(
+        &["logs"],
+        r#"{"level":"info","subject":"quix.second@0.0.1","body":{"message":"Hello"}}"#,
+    );
+    let start = Instant::now();
+    while !sandbox.output().contains("[quix.second] Hello") {
+        assert!(start.elapsed() < TIMEOUT, "the log was never tagged");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn link_keeps_a_build_per_app() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("builds");
+    monorepo(&sandbox);
+    sandbox.link(&hub, &["--all"]);
+
+    let start = Instant::now();
+    while hub.count("link") < 2 || hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the apps were never linked");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // ? Both apps build at once, the second one ending first doesn't end the other.
+    hub.emit_to(
+        &["events"],
+        r#"{"subject":"quix.e2e@0.0.1","body":{"code":"start","details":{"builders":["react"]}}}"#,
+    );
+    hub.emit_to(
+        &["events"],
+        r#"{"subject":"quix.second@0.0.1","body":{"code":"start","details":{"builders":["node"]}}}"#,
+    );
+    hub.emit_to(
+        &["events"],
+        r#"{"subject":"quix.second@0.0.1","body":{"code":"success","details":{"timings":{"node":200}}}}"#,
+    );
+    hub.emit_to(
+        &["events"],
+        r#"{"subject":"quix.e2e@0.0.1","body":{"code":"success","details":{}}}"#,
+    );
+
+    let start = Instant::now();
+    while !sandbox.output().contains("quix.e2e linked in") {
+        assert!(start.elapsed() < TIMEOUT, "the builds never ended");
+        thread::sleep(Duration::from_millis(50));
+    }
+    let output = sandbox.output();
+    assert!(output.contains("Building quix.e2e: react..."), "{}", output);
+    assert!(
+        output.contains("Building quix.second: node..."),
+        "{}",
+        output
+    );
+    assert!(output.contains("quix.second linked in "), "{}", output);
+    assert!(output.contains("(node 0.2s)"), "{}", output);
+    // ? Its builders were still running, so they're timed with the local clock.
+    let e2e = output
+        .lines()
+        .find(|l| l.contains("quix.e2e linked in"))
+        .unwrap();
+    assert!(e2e.contains("(react "), "{}", e2e);
+}
+
+#[test]
+fn link_links_the_given_paths() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("paths");
+    monorepo(&sandbox);
+    sandbox.link(&hub, &["apps/second/react"]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    thread::sleep(Duration::from_millis(500));
+
+    assert_eq!(links_of(&hub, "quix.second@0.0.1"), 1);
+    assert_eq!(hub.count("link"), 1);
+}
+
+#[test]
+fn link_sends_the_bundle_again_when_the_builder_asks() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("initial");
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    // ? Wait for the three streams (build, status and logs).
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never connected");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    hub.emit(r#"{"level":"error","body":{"code":"initial_link_required"}}"#);
+
+    let start = Instant::now();
+    while hub.count("link") < 2 {
+        assert!(start.elapsed() < TIMEOUT, "the bundle was never sent again");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn link_survives_unknown_build_events() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("unknown");
+    sandbox.link(&hub, &[]);
+
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never connected");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // ? Payloads no model knows, they are rendered (or traced) instead of stopping the streams.
+    hub.emit("not a json");
+    hub.emit(r#"{"body":{"code":42}}"#);
+    hub.emit(r#"{"body":{"code":"teleported"}}"#);
+    hub.emit(r#"{"body":{"code":"fail","details":{"errors":[{"message":"Oops"}]}}}"#);
+    hub.emit(r#"{"level":"error","body":{"code":"initial_link_required"}}"#);
+
+    let start = Instant::now();
+    while hub.count("link") < 2 {
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "the streams stopped on an unknown event"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(sandbox
+        .child
+        .as_mut()
+        .unwrap()
+        .try_wait()
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn link_summarizes_the_build() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("progress");
+    sandbox.link(&hub, &[]);
+
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never connected");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    hub.emit(r#"{"body":{"code":"start","details":{"builders":["react","store"]}}}"#);
+    hub.emit(r#"{"body":{"code":"success","details":{"timings":{"react":3100,"store":400}}}}"#);
+
+    // ? Not a terminal, so the progress comes as plain lines.
+    let start = Instant::now();
+    while !sandbox.output().contains("(react 3.1s, store 0.4s)") {
+        assert!(start.elapsed() < TIMEOUT, "the build was never summarized");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(sandbox.output().contains("Building react, store..."));
+    assert!(sandbox.output().contains("Linked in "));
+}
+
+#[test]
+fn link_outputs_json_lines() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("json");
+    sandbox.link(&hub, &["--output", "json"]);
+
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never connected");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    hub.emit(r#"{"body":{"code":"success","details":{"timings":{"react":3100}}}}"#);
+    fs::write(
+        sandbox.project().join("react/index.tsx"),
+        "export default () => 'json'\n",
+    )
+    .unwrap();
+
+    hub.wait_for(|r| r.route() == "relink", TIMEOUT)
+        .expect("the change was never sent");
+    thread::sleep(Duration::from_millis(500));
+
+    // ? Nothing but JSON lines, each one with its time and event.
+    let records: Vec<Value> = sandbox
+        .output()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("not a JSON line"))
+        .collect();
+    assert!(records.iter().all(|r| r["ts"].is_u64()));
+
+    let find = |event: &str, kind: &str| {
+        records
+            .iter()
+            .find(|r| r["event"] == event && (kind.is_empty() || r["kind"] == kind))
+            .unwrap_or_else(|| panic!("no {} {} record", event, kind))
+    };
+
+    let finished = find("upload_finished", "link");
+    assert_eq!(finished["app"], "quix.e2e@0.0.1");
+    assert!(finished["bytes"].as_u64().unwrap() > 0);
+    assert!(finished["ms"].is_u64());
+    assert_eq!(find("build", "")["timings"]["react"], 3100);
+    assert!(find("file_changed", "")["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("index.tsx"));
+    find("upload_finished", "relink");
+}
+
+#[test]
+fn link_streams_the_logs_at_the_log_level() {
+    for (args, level) in [
+        (&[][..], "level=info"),
+        (&["-v"][..], "level=debug"),
+        (&["--log-level", "warn"][..], "level=warn"),
+        (&["--quiet"][..], "level=error"),
+    ] {
+        let hub = MockHub::start();
+        let mut sandbox = Sandbox::new("level");
+        sandbox.link(&hub, args);
+
+        let logs = hub
+            .wait_for(|r| r.route() == "logs", TIMEOUT)
+            .expect("the logs were never streamed");
+        assert!(logs.path.ends_with(level), "{:?} => {}", args, logs.path);
+    }
+}
+
+#[test]
+fn link_flags_take_precedence_over_the_log_level_env() {
+    for (args, level) in [
+        (&[][..], "level=warn"),
+        (&["-v"][..], "level=debug"),
+        (&["--log-level", "info"][..], "level=info"),
+        (&["--quiet"][..], "level=error"),
+    ] {
+        let hub = MockHub::start();
+        let mut sandbox = Sandbox::new("level-env");
+        sandbox.envs.push(("QUIX_LOG_LEVEL", "warn".to_string()));
+        sandbox.link(&hub, args);
+
+        let logs = hub
+            .wait_for(|r| r.route() == "logs", TIMEOUT)
+            .expect("the logs were never streamed");
+        assert!(logs.path.ends_with(level), "{:?} => {}", args, logs.path);
+    }
+}
+
+#[test]
+fn link_quiet_only_logs_the_errors() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("quiet");
+    sandbox.link(&hub, &["--quiet"]);
+
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never connected");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    hub.emit(r#"{"body":{"code":"success","details":{"timings":{"react":3100}}}}"#);
+    hub.emit(r#"{"level":"error","body":{"message":"Boom"}}"#);
+
+    let start = Instant::now();
+    while !sandbox.output().contains("Boom") {
+        assert!(start.elapsed() < TIMEOUT, "the error was never logged");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(!sandbox.output().contains("Linked in"));
+    assert!(!sandbox.output().contains("Successfully sent"));
+}
+
+#[test]
+fn link_filters_the_app_logs() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("filter");
+    // ? The quix.json sets the level and the app, the command line adds the regexes.
+    fs::write(
+        sandbox.project().join("quix.json"),
+        r#"{"logs":{"apps":["quix.e2e"],"level":"warn","exclude":["ignored"]}}"#,
+    )
+    .unwrap();
+    // ? Not a terminal, the colors have to be forced to see the highlight.
+    sandbox.envs.push(("CLICOLOR_FORCE", "1".to_string()));
+    sandbox.link(&hub, &["--logs-include", r"order \d+"]);
+
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never connected");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    for line in [
+        r#"{"level":"error","subject":"other.app@1.0.0","body":{"message":"Other order 1"}}"#,
+        r#"{"level":"info","subject":"quix.e2e@0.0.1","body":{"message":"Chatty order 2"}}"#,
+        r#"{"level":"error","subject":"quix.e2e@0.0.1","body":{"message":"No match"}}"#,
+        r#"{"level":"error","subject":"quix.e2e@0.0.1","body":{"message":"An ignored order 3"}}"#,
+        r#"{"level":"warning","subject":"quix.e2e@0.0.1","body":{"message":"Failed order 4"}}"#,
+    ] {
+        hub.emit_to(&["logs"], line);
+    }
+
+    let start = Instant::now();
+    while !sandbox.output().contains("Failed ") {
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "the matching log was never shown"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // ? The match is highlighted, and the other lines are left out.
+    let output = sandbox.output();
+    assert!(output.contains("43;30morder 4\x1b[0m"), "{}", output);
+    for hidden in ["Other", "Chatty", "No match", "ignored"] {
+        assert!(!output.contains(hidden), "{} was shown", hidden);
+    }
+}
+
+#[test]
+fn link_exits_with_10_on_an_invalid_log_filter() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("badfilter");
+    sandbox.link(&hub, &["--logs-include", "(unclosed"]);
+
+    assert_eq!(sandbox.exit_code(), Some(10));
+    assert_eq!(hub.count("link"), 0);
+}
+
+#[test]
+fn link_exits_with_9_when_the_bundle_fails() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("zipfail");
+    // ? A file name that can't go in the bundle, found after the upload started.
+    fs::write(
+        sandbox
+            .project()
+            .join(OsStr::from_bytes(b"react/z\xff.tsx")),
+        "export {}\n",
+    )
+    .unwrap();
+    sandbox.link(&hub, &[]);
+
+    assert_eq!(sandbox.exit_code(), Some(9));
+    let output = sandbox.output();
+    assert!(
+        !output.contains("Successfully sent the bundle"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn link_resolves_the_builder_again_when_it_moves() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("moved");
+    hub.fail_next("link", 503);
+    sandbox.link(&hub, &[]);
+
+    let start = Instant::now();
+    while hub.count("link") < 2 {
+        assert!(start.elapsed() < TIMEOUT, "the bundle was never sent again");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // ? Once for the link, and again after the builder failed.
+    assert_eq!(hub.count("availability"), 2);
+}
+
+#[test]
+fn link_exits_with_3_without_a_session() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("session");
+    fs::remove_file(sandbox.root.join("home/.config/configstore/vtex.json")).unwrap();
+    sandbox.link(&hub, &[]);
+
+    assert_eq!(sandbox.exit_code(), Some(3));
+    assert!(hub.requests().is_empty());
+}
+
+#[test]
+fn link_exits_with_4_on_an_invalid_manifest() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("manifest");
+    fs::write(
+        sandbox.project().join("manifest.json"),
+        r#"{"vendor":"quix","name":"e2e","version":"0.0.1","builders":{"node":"6.x"}}"#,
+    )
+    .unwrap();
+    sandbox.link(&hub, &[]);
+
+    assert_eq!(sandbox.exit_code(), Some(4));
+    assert!(hub.requests().is_empty());
+}
+
+#[test]
+fn link_exits_with_5_when_the_builder_refuses_the_bundle() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("refused");
+    hub.fail_next("link", 403);
+    sandbox.link(&hub, &[]);
+
+    assert_eq!(sandbox.exit_code(), Some(5));
+    assert_eq!(hub.count("link"), 1);
+}
+
+#[test]
+fn link_follows_a_version_bump_in_the_manifest() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("bump");
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    let project = sandbox.project();
+    let start = Instant::now();
+    let link = loop {
+        fs::write(
+            project.join("manifest.json"),
+            r#"{"vendor":"quix","name":"e2e","version":"0.0.2","builders":{"react":"3.x"}}"#,
+        )
+        .unwrap();
+
+        let bumped = |r: &Request| r.route() == "link" && r.path.contains("quix.e2e@0.0.2");
+        if let Some(link) = hub.wait_for(bumped, Duration::from_secs(3)) {
+            break link;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "the new version was never linked"
+        );
+    };
+
+    // ? A new version is a new app for the builder, with its own sticky host.
+    assert!(hub
+        .requests()
+        .iter()
+        .any(|r| r.route() == "availability" && r.path.contains("quix.e2e@0.0.2")));
+    assert!(bundle_files(&link).contains(&"manifest.json".to_string()));
+}
+
+#[test]
+fn link_unlinks_the_app_when_stopped() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("stop");
+    sandbox.link(&hub, &["--unlink"]);
+
+    // ? The streams start right before the session loop, which handles the Ctrl-C.
+    let start = Instant::now();
+    while hub.streams() < 3 {
+        assert!(start.elapsed() < TIMEOUT, "the streams never started");
+        thread::sleep(Duration::from_millis(50));
+    }
+    thread::sleep(Duration::from_secs(1));
+
+    sandbox.interrupt();
+
+    assert_eq!(sandbox.exit_code(), Some(0));
+    let unlink = hub
+        .wait_for(|r| r.route() == "unlink", TIMEOUT)
+        .expect("the app was never unlinked");
+    assert!(unlink
+        .path
+        .ends_with("/apps/v0/mock/e2e/links/quix.e2e@0.0.1"));
+}
+
+#[test]
+fn link_once_exits_when_the_build_succeeds() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("once");
+    sandbox.link(&hub, &["--once", "--unlink"]);
+
+    // ? The bundle is only sent once the build stream listens.
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    hub.emit(r#"{"body":{"code":"success","details":{"timings":{"react":3100}}}}"#);
+
+    assert_eq!(sandbox.exit_code(), Some(0));
+    assert!(sandbox.output().contains("built successfully"));
+    assert_eq!(hub.count("unlink"), 1);
+}
+
+#[test]
+fn link_once_exits_with_11_when_the_build_fails() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("oncefail");
+    sandbox.link(&hub, &["--once"]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    hub.emit(r#"{"body":{"code":"fail","details":{"errors":[{"message":"Oops"}]}}}"#);
+
+    assert_eq!(sandbox.exit_code(), Some(11));
+    let output = sandbox.output();
+    assert!(output.contains("Oops"), "{}", output);
+    assert!(
+        output.contains("The build of quix.e2e failed."),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn link_once_exits_with_11_when_interrupted() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("onceinterrupted");
+    sandbox.link(&hub, &["--once"]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    hub.emit_to(&["events"], "link_interrupted");
+
+    assert_eq!(sandbox.exit_code(), Some(11));
+    let output = sandbox.output();
+    assert!(output.contains("Link interrupted."), "{}", output);
+}
+
+#[test]
+fn link_once_exits_with_12_on_timeout() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("oncetimeout");
+    sandbox.link(&hub, &["--once", "--timeout", "1"]);
+
+    assert_eq!(sandbox.exit_code(), Some(12));
+}
+
+#[test]
+fn link_stops_during_a_hung_relink() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("hung");
+    sandbox.link(&hub, &[]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+    wait_for_the_watcher(&hub, &sandbox);
+
+    hub.hang_next("relink");
+    fs::write(
+        sandbox.project().join("react/index.tsx"),
+        "export default 1\n",
+    )
+    .unwrap();
+
+    let start = Instant::now();
+    while hub.count("relink") < 2 {
+        assert!(start.elapsed() < TIMEOUT, "the change was never relinked");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // ? The relink never ends, the Ctrl-C still does.
+    sandbox.interrupt();
+    assert_eq!(sandbox.exit_code(), Some(0));
+}
+
+#[test]
+fn link_unlinks_when_stopped_during_the_upload() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("hungupload");
+    hub.hang_next("link");
+    sandbox.link(&hub, &["--unlink"]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    // ? The upload never ends, the Ctrl-C still does, and leaves the workspace as it was.
+    sandbox.interrupt();
+    assert_eq!(sandbox.exit_code(), Some(0));
+    assert_eq!(hub.count("unlink"), 1);
+}
+
+#[test]
+fn link_once_unlinks_when_stopped_during_the_build() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("oncestop");
+    sandbox.link(&hub, &["--once", "--unlink"]);
+
+    hub.wait_for(|r| r.route() == "link", TIMEOUT)
+        .expect("the bundle was never sent");
+
+    sandbox.interrupt();
+    assert_eq!(sandbox.exit_code(), Some(0));
+    assert_eq!(hub.count("unlink"), 1);
+    assert!(sandbox.output().contains("Stopping the link"));
+}
+
+#[test]
+fn unlink_defaults_to_the_project_app() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("unlink");
+    sandbox.run(&hub, "unlink", &[]);
+
+    assert_eq!(sandbox.exit_code(), Some(0));
+    let unlink = hub.requests().pop().expect("nothing was unlinked");
+    assert_eq!(unlink.route(), "unlink");
+    assert!(unlink
+        .path
+        .ends_with("/apps/v0/mock/e2e/links/quix.e2e@0.0.1"));
+}
+
+#[test]
+fn unlink_all_unlinks_every_app() {
+    let hub = MockHub::start();
+    let mut sandbox = Sandbox::new("unlink-all");
+    sandbox.run(&hub, "unlink", &["--all"]);
+
+    assert_eq!(sandbox.exit_code(), Some(0));
+    let unlink = hub.requests().pop().expect("nothing was unlinked");
+    assert_eq!(unlink.route(), "unlink");
+    assert!(unlink.path.ends_with("/apps/v0/mock/e2e/links"));
+}
@@ -0,0 +1,373 @@
+//! # Mock builder-hub
+//! A local stand-in for the VTEX IO services used by `quix link`, so it can run offline.
+//! It records every request, and keeps the Colossus streams open to push events through them.
+//!
+//! ## Endpoints
+//! - `POST .../availability/{app}`: Answers with a sticky host.
+//! - `POST .../link/{app}`: Accepts the bundle (chunked or not).
+//! - `PUT .../relink/{app}`: Accepts the changed files.
+//! - `POST .../clean/{app}`: Accepts the clean.
+//! - `GET /colossus/...`: Opens an event stream, fed by `MockHub::emit`.
+//!
+//! ## Examples
+//! ```rust
+//! let hub = MockHub::start();
+//! // Run quix with QUIX_BUILDER_URL and QUIX_COLOSSUS_URL set to `hub.url`...
+//! let link = hub.wait_for(|r| r.route() == "link", Duration::from_secs(10));
+//! ```
+
+#![allow(dead_code)] // Shared by the tests and the example, each one uses a part of it.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// # Sticky host
+/// The host handed by the availability endpoint, as `host:port`, like the real one.
+pub const STICKY_HOST: &str = "mock-builder:8080";
+
+/// # Request struct
+/// A request received by the mock.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String, // Path and query
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// # Header
+    /// The value of a header, the name is case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// # Route
+    /// The endpoint the request hit: `availability`, `link`, `relink`, `clean`, `events`, `logs` or `unknown`.
+    pub fn route(&self) -> &'static str {
+        let path = self.path.split('?').next().unwrap_or_default();
+
+        if path.starts_with("/colossus/") {
+            return if path.ends_with("/logs") {
+                "logs"
+            } else {
+                "events"
+            };
+        }
+
+        if path.starts_with("/apps/") && self.method == "DELETE" && path.contains("/links") {
+            return "unlink";
+        }
+
+        ["availability", "link", "relink", "clean"]
+            .into_iter()
+            .find(|route| path.contains(&format!("/_v/builder/0/{}/", route)))
+            .unwrap_or("unknown")
+    }
+}
+
+#[derive(Default)]
+struct State {
+    requests: Mutex<Vec<Request>>,
+    arrived: Condvar,
+    streams: Mutex<Vec<(&'static str, TcpStream)>>, // Route => open stream
+    failures: Mutex<VecDeque<(&'static str, u16)>>, // Route => status, answered once
+    hangs: Mutex<VecDeque<&'static str>>,           // Routes never answered, once
+    hung: Mutex<Vec<TcpStream>>,                    // Requests kept waiting
+    log: bool,
+}
+
+/// # MockHub struct
+/// The running mock, it stops with the process.
+pub struct MockHub {
+    pub url: String, // Base URL, for both the builder and the Colossus
+    state: Arc<State>,
+}
+
+impl MockHub {
+    /// # Start the mock.
+    /// Listens on a random local port.
+    pub fn start() -> MockHub {
+        MockHub::bind("127.0.0.1:0", false).unwrap()
+    }
+
+    /// # Bind the mock.
+    /// Listens on `addr`, printing each request if `log` is set.
+    pub fn bind(addr: impl ToSocketAddrs, log: bool) -> io::Result<MockHub> {
+        let listener = TcpListener::bind(addr)?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let state = Arc::new(State {
+            log,
+            ..State::default()
+        });
+
+        let c_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = c_state.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream, &state) {
+                        if state.log {
+                            eprintln!("!!! {}", e);
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(MockHub { url, state })
+    }
+
+    /// # Requests
+    /// Every request received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// # Hang the next request.
+    /// The next request to `route` is never answered, to reproduce a builder that hangs.
+    pub fn hang_next(&self, route: &'static str) {
+        self.state.hangs.lock().unwrap().push_back(route);
+    }
+
+    /// # Wait for a request.
+    /// Blocks until a request matching `predicate` arrives, or the timeout passes.
+    pub fn wait_for(
+        &self,
+        predicate: impl Fn(&Request) -> bool,
+        timeout: Duration,
+    ) -> Option<Request> {
+        let deadline = Instant::now() + timeout;
+        let mut requests = self.state.requests.lock().unwrap();
+
+        loop {
+            if let Some(request) = requests.iter().find(|r| predicate(r)) {
+                return Some(request.clone());
+            }
+
+            let left = deadline.checked_duration_since(Instant::now())?;
+            requests = self.state.arrived.wait_timeout(requests, left).unwrap().0;
+        }
+    }
+
+    /// # Count
+    /// How many requests hit a route so far.
+    pub fn count(&self, route: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|r| r.route() == route)
+            .count()
+    }
+
+    /// # Fail the next request.
+    /// The next request to `route` is answered with `status`, to reproduce builder errors.
+    pub fn fail_next(&self, route: &'static str, status: u16) {
+        self.state
+            .failures
+            .lock()
+            .unwrap()
+            .push_back((route, status));
+    }
+
+    /// # Streams
+    /// How many event streams are open.
+    pub fn streams(&self) -> usize {
+        self.state.streams.lock().unwrap().len()
+    }
+
+    /// # Emit an event.
+    /// Sends the `data` to every open event stream, dropping the closed ones.
+    pub fn emit(&self, data: &str) {
+        self.emit_to(&["events", "logs"], data)
+    }
+
+    /// # Emit an event to some routes.
+    /// Sends the `data` to the open streams of the `routes` (`events` or `logs`), dropping the closed ones.
+    pub fn emit_to(&self, routes: &[&str], data: &str) {
+        let event = format!("data: {}\n\n", data);
+
+        self.state
+            .streams
+            .lock()
+            .unwrap()
+            .retain_mut(|(route, stream)| {
+                !routes.contains(route) || stream.write_all(event.as_bytes()).is_ok()
+            });
+    }
+}
+
+/// # Handle a connection.
+/// Reads a single request and answers it, the event streams are kept open.
+fn handle(stream: TcpStream, state: &State) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_request(&mut reader)?;
+    let route = request.route();
+
+    if state.log {
+        eprintln!(
+            "{} {} ({} bytes)",
+            request.method,
+            request.path,
+            request.body.len()
+        );
+    }
+
+    let failure = {
+        let mut failures = state.failures.lock().unwrap();
+        let index = failures.iter().position(|(r, _)| *r == route);
+        index.and_then(|i| failures.remove(i))
+    };
+
+    let hang = {
+        let mut hangs = state.hangs.lock().unwrap();
+        let index = hangs.iter().position(|r| *r == route);
+        index.and_then(|i| hangs.remove(i)).is_some()
+    };
+
+    state.requests.lock().unwrap().push(request);
+    state.arrived.notify_all();
+
+    if hang {
+        state.hung.lock().unwrap().push(stream);
+        return Ok(());
+    }
+
+    let mut stream = stream;
+
+    if let Some((_, status)) = failure {
+        let body = format!(
+            r#"{{"code":"mock_error","message":"Mocked {} on {}"}}"#,
+            status, route
+        );
+        return respond(&mut stream, status, &[], &body);
+    }
+
+    match route {
+        "availability" => respond(&mut stream, 200, &[("x-vtex-sticky-host", STICKY_HOST)], ""),
+        "link" | "relink" | "clean" | "unlink" => {
+            respond(&mut stream, 200, &[], r#"{"code":"ok"}"#)
+        }
+        "events" | "logs" => {
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n",
+            )?;
+            stream.flush()?;
+            state.streams.lock().unwrap().push((route, stream));
+            Ok(())
+        }
+        _ => respond(
+            &mut stream,
+            404,
+            &[],
+            r#"{"code":"not_found","message":"Unknown route"}"#,
+        ),
+    }
+}
+
+/// # Read a request.
+/// Parses the request line, the headers and the body, chunked or with a length.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = vec![];
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((key, value)) = header.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: vec![],
+    };
+
+    if request
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            if size == 0 {
+                // ? Skip the trailers, up to the empty line.
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                }
+                break;
+            }
+
+            let start = request.body.len();
+            request.body.resize(start + size, 0);
+            reader.read_exact(&mut request.body[start..])?;
+
+            line.clear();
+            reader.read_line(&mut line)?; // The CRLF after each chunk.
+        }
+    } else if let Some(length) = request
+        .header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+    {
+        request.body.resize(length, 0);
+        reader.read_exact(&mut request.body)?;
+    }
+
+    Ok(request)
+}
+
+/// # Respond
+/// Writes a whole response, closing the connection after it.
+fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> io::Result<()> {
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+
+    for (key, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", key, value));
+    }
+
+    response.push_str("\r\n");
+    response.push_str(body);
+
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}